@@ -0,0 +1,8 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+// The first line of the input is the query, the rest the document, see gsjf::harness::split
+fuzz_target!(|data: &[u8]| {
+    gsjf::harness::run(data);
+});
@@ -0,0 +1,266 @@
+//! An exact decimal number of any size, for values f64 cannot hold such as
+//! `12345678901234567890.000001`.
+//!
+//! A [Decimal] is a sign, a string of significant digits and a power of ten. It is kept
+//! normalised, without leading or trailing zeros in its digits, so equal numbers compare
+//! and hash equal however they were written: `1.50`, `15e-1` and `0.15E1` are one value.
+
+use std::cmp::Ordering;
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
+use crate::typed::{Number, ValueError};
+
+/// How many zeros [Decimal]'s Display pads with before switching to an exponent
+const PLAIN_ZEROS: i64 = 21;
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub struct Decimal {
+    negative: bool,
+    /// ASCII digits without leading or trailing zeros, "0" for zero
+    digits: String,
+    exponent: i64,
+}
+
+impl Decimal {
+    /// Builds ±[integer][fraction] × 10^[exponent], where both are ASCII digits
+    fn new(negative: bool, integer: &str, fraction: &str, exponent: i64) -> Result<Decimal, ValueError> {
+        let exponent: i64 = exponent.checked_sub(fraction.len() as i64).ok_or(ValueError::ExponentOutOfRange)?;
+        let all: String = [integer, fraction].concat();
+        let significant: &str = all.trim_start_matches('0');
+        let digits: &str = significant.trim_end_matches('0');
+        if digits.is_empty() {
+            return Ok(Decimal::zero())
+        }
+        let trailing: i64 = (significant.len() - digits.len()) as i64;
+        let exponent: i64 = exponent.checked_add(trailing).ok_or(ValueError::ExponentOutOfRange)?;
+        // keeps Decimal::point from overflowing too
+        exponent.checked_add(digits.len() as i64).ok_or(ValueError::ExponentOutOfRange)?;
+        Ok(Decimal{ negative, digits: digits.to_string(), exponent })
+    }
+
+    pub fn zero() -> Decimal {
+        Decimal{ negative: false, digits: "0".to_string(), exponent: 0 }
+    }
+
+    /// Fails unless [lexeme] is exactly one JSON number
+    pub fn parse(lexeme: &[u8]) -> Result<Decimal, ValueError> {
+        Number::parse(lexeme)?.as_decimal()
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.negative
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.digits == "0"
+    }
+
+    pub fn is_integer(&self) -> bool {
+        self.exponent >= 0
+    }
+
+    /// The significant digits, the value is ±digits × 10^[Decimal::exponent]
+    pub fn digits(&self) -> &str {
+        &self.digits
+    }
+
+    pub fn exponent(&self) -> i64 {
+        self.exponent
+    }
+
+    /// The magnitude, exact or an overflow, never rounded
+    fn magnitude(&self) -> Result<u128, ValueError> {
+        if !self.is_integer() {
+            return Err(ValueError::NotAnInteger)
+        }
+        let mut magnitude: u128 = self.digits.parse().map_err(|_| ValueError::Overflow)?;
+        for _ in 0..self.exponent {
+            magnitude = magnitude.checked_mul(10).ok_or(ValueError::Overflow)?;
+        }
+        Ok(magnitude)
+    }
+
+    pub fn to_i128(&self) -> Result<i128, ValueError> {
+        let magnitude: u128 = self.magnitude()?;
+        match self.negative {
+            true => 0i128.checked_sub_unsigned(magnitude).ok_or(ValueError::Overflow),
+            false => i128::try_from(magnitude).map_err(|_| ValueError::Overflow)
+        }
+    }
+
+    pub fn to_u128(&self) -> Result<u128, ValueError> {
+        match self.negative {
+            true => Err(ValueError::Overflow),
+            false => self.magnitude()
+        }
+    }
+
+    /// Where the decimal point falls counting from the first digit, 1 for 1.5 and -1 for 0.015
+    fn point(&self) -> i64 {
+        self.digits.len() as i64 + self.exponent
+    }
+
+    fn cmp_magnitude(&self, other: &Decimal) -> Ordering {
+        match (self.is_zero(), other.is_zero()) {
+            (true, true) => return Ordering::Equal,
+            (true, false) => return Ordering::Less,
+            (false, true) => return Ordering::Greater,
+            _ => {}
+        }
+        // digits never start with a zero, so the further out point is the larger number
+        self.point().cmp(&other.point()).then_with(|| {
+            let width: usize = self.digits.len().max(other.digits.len());
+            let pad = |digits: &str| format!("{:0<width$}", digits, width = width);
+            pad(&self.digits).cmp(&pad(&other.digits))
+        })
+    }
+}
+
+impl Ord for Decimal {
+    fn cmp(&self, other: &Decimal) -> Ordering {
+        match (self.negative, other.negative) {
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+            (false, false) => self.cmp_magnitude(other),
+            (true, true) => other.cmp_magnitude(self),
+        }
+    }
+}
+
+impl PartialOrd for Decimal {
+    fn partial_cmp(&self, other: &Decimal) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl FromStr for Decimal {
+    type Err = ValueError;
+
+    fn from_str(lexeme: &str) -> Result<Decimal, ValueError> {
+        Decimal::parse(lexeme.as_bytes())
+    }
+}
+
+impl<'a> Number<'a> {
+    /// The exact value, however many digits it has. Zero is zero whatever its exponent,
+    /// any other number fails with [ValueError::ExponentOutOfRange] when its power of ten
+    /// lies beyond ±2^63, e.g. `1e-99999999999999999999`, rather than being rounded.
+    pub fn as_decimal(&self) -> Result<Decimal, ValueError> {
+        let lexeme: &str = self.as_str();
+        let (negative, unsigned): (bool, &str) = match lexeme.strip_prefix('-') {
+            Some(unsigned) => (true, unsigned),
+            None => (false, lexeme)
+        };
+        let (mantissa, exponent): (&str, &str) = match unsigned.find(['e', 'E']) {
+            Some(idx) => (&unsigned[..idx], &unsigned[idx + 1..]),
+            None => (unsigned, "0")
+        };
+        let (integer, fraction): (&str, &str) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+        if integer.bytes().chain(fraction.bytes()).all(|digit| digit == b'0') {
+            return Ok(Decimal::zero())
+        }
+        let exponent: i64 = exponent.parse().map_err(|_| ValueError::ExponentOutOfRange)?;
+        Decimal::new(negative, integer, fraction, exponent)
+    }
+}
+
+macro_rules! from_integer {
+    ($($integer:ty),*) => {
+        $(impl From<$integer> for Decimal {
+            fn from(value: $integer) -> Decimal {
+                let text: String = value.to_string();
+                let (negative, magnitude): (bool, &str) = match text.strip_prefix('-') {
+                    Some(magnitude) => (true, magnitude),
+                    None => (false, text.as_str())
+                };
+                // plain decimal digits cannot overflow the exponent
+                Decimal::new(negative, magnitude, "", 0).unwrap_or_else(|_| Decimal::zero())
+            }
+        })*
+    };
+}
+
+from_integer!(i64, u64, i128, u128);
+
+impl fmt::Display for Decimal {
+    /// Writes valid JSON: plain notation unless that takes more than 21 padding zeros
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.negative {
+            write!(f, "-")?;
+        }
+        let point: i64 = self.point();
+        if self.exponent >= 0 && self.exponent <= PLAIN_ZEROS {
+            write!(f, "{}{}", self.digits, "0".repeat(self.exponent as usize))
+        } else if self.exponent < 0 && point > 0 {
+            let (integer, fraction) = self.digits.split_at(point as usize);
+            write!(f, "{}.{}", integer, fraction)
+        } else if self.exponent < 0 && point >= -PLAIN_ZEROS {
+            write!(f, "0.{}{}", "0".repeat(-point as usize), self.digits)
+        } else {
+            let (first, rest) = self.digits.split_at(1);
+            match rest.is_empty() {
+                true => write!(f, "{}e{}", first, point - 1),
+                false => write!(f, "{}.{}e{}", first, rest, point - 1)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::decimal::Decimal;
+    use crate::typed::ValueError;
+
+    fn decimal(lexeme: &str) -> Decimal {
+        lexeme.parse().unwrap()
+    }
+
+    #[test]
+    fn test_exact() {
+        let amount: Decimal = decimal("12345678901234567890.000001");
+        assert_eq!(amount.digits(), "12345678901234567890000001");
+        assert_eq!(amount.exponent(), -6);
+        assert_eq!(amount.to_string(), "12345678901234567890.000001");
+        assert_eq!(decimal("1.50"), decimal("15e-1"));
+        assert_eq!(decimal("0.15E1"), decimal("1.5"));
+        assert_eq!(decimal("-0.0"), Decimal::zero());
+        assert_eq!("1.5.0".parse::<Decimal>(), Err(ValueError::Malformed));
+        assert_eq!("1e99999999999999999999".parse::<Decimal>(), Err(ValueError::ExponentOutOfRange));
+        assert_eq!("1e-99999999999999999999".parse::<Decimal>(), Err(ValueError::ExponentOutOfRange));
+        assert_eq!("12.5e9223372036854775807".parse::<Decimal>(), Err(ValueError::ExponentOutOfRange));
+        assert_eq!(decimal("0e99999999999999999999"), Decimal::zero());
+        assert_eq!(decimal("-0.000e-99999999999999999999"), Decimal::zero());
+        assert_eq!(decimal("1e+0009"), decimal("1000000000"));
+    }
+
+    #[test]
+    fn test_display() {
+        for (lexeme, shown) in [("0", "0"), ("-12.50", "-12.5"), ("1e3", "1000"), ("0.001", "0.001"), ("1e30", "1e30"),
+                                ("12.5e-30", "1.25e-29"), ("-4e-2", "-0.04")] {
+            assert_eq!(decimal(lexeme).to_string(), shown, "{}", lexeme);
+            assert_eq!(decimal(shown), decimal(lexeme));
+        }
+    }
+
+    #[test]
+    fn test_integers() {
+        assert_eq!(decimal("170141183460469231731687303715884105727").to_i128(), Ok(i128::MAX));
+        assert_eq!(decimal("-170141183460469231731687303715884105728").to_i128(), Ok(i128::MIN));
+        assert_eq!(decimal("170141183460469231731687303715884105728").to_i128(), Err(ValueError::Overflow));
+        assert_eq!(decimal("34028236692093846346337460743176821145.5e1").to_u128(), Ok(u128::MAX));
+        assert_eq!(decimal("3.40282366920938463463374607431768211456e38").to_u128(), Err(ValueError::Overflow));
+        assert_eq!(decimal("1.5").to_i128(), Err(ValueError::NotAnInteger));
+        assert_eq!(decimal("-1").to_u128(), Err(ValueError::Overflow));
+        assert_eq!(Decimal::from(u128::MAX).to_u128(), Ok(u128::MAX));
+        assert_eq!(Decimal::from(-1200i64), decimal("-1.2e3"));
+    }
+
+    #[test]
+    fn test_ordering() {
+        let mut sorted: Vec<Decimal> = ["10", "-2.5", "0", "9.99", "-10", "1e-9", "0.1"].iter().map(|lexeme| decimal(lexeme)).collect();
+        sorted.sort();
+        let shown: Vec<String> = sorted.iter().map(Decimal::to_string).collect();
+        assert_eq!(shown, vec!("-10", "-2.5", "0", "0.000000001", "0.1", "9.99", "10"));
+    }
+}
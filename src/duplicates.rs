@@ -0,0 +1,88 @@
+//! What a key that appears more than once in the same object means.
+//!
+//! RFC 8259 leaves it open, and parsers disagree: some keep the first value, most keep
+//! the last, a few reject the document. Two components reading the same document with
+//! different answers is a classic way to smuggle a value past a check, so every lookup
+//! that can see duplicates takes a [DuplicateKeys] and resolves them the same way.
+//!
+//! Keys are compared with their escapes resolved, so `"\u0061"` and `"a"` are the same
+//! key to the validator, to value trees and to every lookup that takes a policy.
+
+use crate::parser::{ErrorKind, ParseError};
+
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum DuplicateKeys {
+    /// A repeated key is an error reporting both occurrences
+    Error,
+    /// The first occurrence is used, what lookups do when not told otherwise
+    #[default]
+    FirstWins,
+    /// The last occurrence is used, like JavaScript's JSON.parse
+    LastWins,
+}
+
+impl DuplicateKeys {
+    /// Picks among every occurrence of a key, given in document order as the offset
+    /// where the key starts and what it maps to
+    pub(crate) fn resolve<T>(&self, found: Vec<(usize, T)>) -> Result<Option<T>, ParseError> {
+        if let (DuplicateKeys::Error, [(first, _), (second, _), ..]) = (self, found.as_slice()) {
+            return Err(ParseError{ kind: ErrorKind::DuplicateKey(*first), offset: *second })
+        }
+        let chosen: Option<(usize, T)> = match self {
+            DuplicateKeys::LastWins => found.into_iter().last(),
+            _ => found.into_iter().next()
+        };
+        Ok(chosen.map(|(_, value)| value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::duplicates::DuplicateKeys;
+    use crate::parser::{ErrorKind, ParseError};
+    use crate::reader::{JSONReader, Reader};
+    use crate::validation::Validator;
+
+    const JSON: &[u8] = br#"{"id": 1, "name": "alex", "id": 2}"#;
+
+    #[test]
+    fn test_resolve() {
+        assert_eq!(DuplicateKeys::FirstWins.resolve(vec!((1, 'a'), (5, 'b'))), Ok(Some('a')));
+        assert_eq!(DuplicateKeys::LastWins.resolve(vec!((1, 'a'), (5, 'b'))), Ok(Some('b')));
+        assert_eq!(DuplicateKeys::Error.resolve(vec!((1, 'a'))), Ok(Some('a')));
+        assert_eq!(DuplicateKeys::Error.resolve(Vec::<(usize, char)>::new()), Ok(None));
+        assert_eq!(DuplicateKeys::Error.resolve(vec!((1, 'a'), (5, 'b'))), Err(ParseError{ kind: ErrorKind::DuplicateKey(1), offset: 5 }));
+    }
+
+    #[test]
+    fn test_check_unique_keys() {
+        assert_eq!(JSONReader::new(JSON).check_unique_keys(), Err(ParseError{ kind: ErrorKind::DuplicateKey(2), offset: 27 }));
+        assert_eq!(JSONReader::new(JSON).check_unique_keys().unwrap_err().to_string(), "duplicate key (first at offset 2) at offset 27");
+        assert!(JSONReader::new(JSON).check().is_ok());
+        // escapes are resolved before comparing
+        assert_eq!(JSONReader::new(br#"{"\u0061": 1, "a": 2}"#).check_unique_keys().unwrap_err().kind, ErrorKind::DuplicateKey(2));
+        // the same key in different objects is fine
+        assert!(JSONReader::new(br#"{"a": {"a": 1}, "b": [{"a": 1}, {"a": 2}]}"#).check_unique_keys().is_ok());
+    }
+
+    #[test]
+    fn test_find_key_with_policy() {
+        let value = |policy: DuplicateKeys| {
+            let mut reader: JSONReader = JSONReader::new(JSON);
+            reader.find_key_with_policy(b"id", policy)
+                .map(|found| found.and_then(|_| reader.read_value()).map(|value| &JSON[value.range().0..value.range().1]))
+        };
+        assert_eq!(value(DuplicateKeys::FirstWins), Ok(Some(b"1".as_ref())));
+        assert_eq!(value(DuplicateKeys::LastWins), Ok(Some(b"2".as_ref())));
+        assert_eq!(value(DuplicateKeys::Error), Err(ParseError{ kind: ErrorKind::DuplicateKey(2), offset: 27 }));
+        let mut reader: JSONReader = JSONReader::new(JSON);
+        assert_eq!(reader.find_key_with_policy(b"name", DuplicateKeys::Error), Ok(Some((11, 15))));
+        assert_eq!(reader.read_value().map(|value| value.range()), Some((19, 23)));
+        // escapes are resolved here too, as the validator does
+        let escaped: &[u8] = br#"{"a": 1, "\u0061": 2}"#;
+        assert_eq!(JSONReader::new(escaped).find_key_with_policy(b"a", DuplicateKeys::Error), Err(ParseError{ kind: ErrorKind::DuplicateKey(2), offset: 10 }));
+        let mut reader: JSONReader = JSONReader::new(escaped);
+        assert_eq!(reader.find_key_with_policy(b"a", DuplicateKeys::LastWins), Ok(Some((10, 16))));
+        assert_eq!(reader.read_value().map(|value| value.range()), Some((19, 20)));
+    }
+}
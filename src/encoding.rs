@@ -0,0 +1,168 @@
+//! Detects how a document is encoded and transcodes it to the UTF-8 the readers expect.
+//!
+//! A byte order mark decides the encoding when present. Without one, the pattern of
+//! zero bytes at the start tells them apart as RFC 4627 describes: JSON text starts
+//! with ASCII, so the first characters of UTF-16 and UTF-32 input carry zero bytes in
+//! a position that gives away both the width and the byte order.
+
+use std::borrow::Cow;
+use std::fmt;
+
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Encoding {
+    UTF8,
+    UTF16LE,
+    UTF16BE,
+    UTF32LE,
+    UTF32BE,
+}
+
+impl Encoding {
+    fn width(&self) -> usize {
+        match self {
+            Encoding::UTF8 => 1,
+            Encoding::UTF16LE | Encoding::UTF16BE => 2,
+            Encoding::UTF32LE | Encoding::UTF32BE => 4,
+        }
+    }
+}
+
+impl fmt::Display for Encoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Encoding::UTF8 => write!(f, "UTF-8"),
+            Encoding::UTF16LE => write!(f, "UTF-16LE"),
+            Encoding::UTF16BE => write!(f, "UTF-16BE"),
+            Encoding::UTF32LE => write!(f, "UTF-32LE"),
+            Encoding::UTF32BE => write!(f, "UTF-32BE"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum EncodingError {
+    /// The input ends partway through a code unit
+    Truncated(Encoding),
+    /// An unpaired surrogate or a value past U+10FFFF, at the given offset of the input
+    Invalid(Encoding, usize),
+}
+
+impl fmt::Display for EncodingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EncodingError::Truncated(encoding) => write!(f, "{} input ends partway through a character", encoding),
+            EncodingError::Invalid(encoding, offset) => write!(f, "invalid {} character at offset {}", encoding, offset),
+        }
+    }
+}
+
+impl std::error::Error for EncodingError {}
+
+/// The encoding of [data] and the length of its byte order mark, 0 without one
+pub fn detect(data: &[u8]) -> (Encoding, usize) {
+    match data {
+        [0xEF, 0xBB, 0xBF, ..] => (Encoding::UTF8, 3),
+        [0xFF, 0xFE, 0, 0, ..] => (Encoding::UTF32LE, 4),
+        [0, 0, 0xFE, 0xFF, ..] => (Encoding::UTF32BE, 4),
+        [0xFF, 0xFE, ..] => (Encoding::UTF16LE, 2),
+        [0xFE, 0xFF, ..] => (Encoding::UTF16BE, 2),
+        [0, 0, 0, _, ..] => (Encoding::UTF32BE, 0),
+        [_, 0, 0, 0, ..] => (Encoding::UTF32LE, 0),
+        [0, _, ..] => (Encoding::UTF16BE, 0),
+        [_, 0, ..] => (Encoding::UTF16LE, 0),
+        _ => (Encoding::UTF8, 0)
+    }
+}
+
+/// The document as UTF-8 without a byte order mark, borrowed when it already was UTF-8.
+/// UTF-8 input is passed through as is, invalid sequences are left for the readers to report.
+pub fn to_utf8(data: &[u8]) -> Result<Cow<'_, [u8]>, EncodingError> {
+    let (encoding, bom): (Encoding, usize) = detect(data);
+    let body: &[u8] = &data[bom..];
+    if encoding == Encoding::UTF8 {
+        return Ok(Cow::Borrowed(body))
+    }
+    if !body.len().is_multiple_of(encoding.width()) {
+        return Err(EncodingError::Truncated(encoding))
+    }
+    let mut utf8: Vec<u8> = Vec::with_capacity(body.len());
+    let mut buf: [u8; 4] = [0; 4];
+    let mut push = |character: char| utf8.extend_from_slice(character.encode_utf8(&mut buf).as_bytes());
+    match encoding {
+        Encoding::UTF16LE | Encoding::UTF16BE => {
+            let units = body.chunks_exact(2).map(|unit| match encoding {
+                Encoding::UTF16LE => u16::from_le_bytes([unit[0], unit[1]]),
+                _ => u16::from_be_bytes([unit[0], unit[1]]),
+            });
+            let mut unit: usize = 0; // characters outside the BMP take two units
+            for character in char::decode_utf16(units) {
+                match character {
+                    Ok(character) => {
+                        push(character);
+                        unit += character.len_utf16();
+                    },
+                    Err(_) => return Err(EncodingError::Invalid(encoding, bom + unit * 2))
+                }
+            }
+        },
+        _ => for (idx, unit) in body.chunks_exact(4).enumerate() {
+            let value: u32 = match encoding {
+                Encoding::UTF32LE => u32::from_le_bytes([unit[0], unit[1], unit[2], unit[3]]),
+                _ => u32::from_be_bytes([unit[0], unit[1], unit[2], unit[3]]),
+            };
+            match char::from_u32(value) {
+                Some(character) => push(character),
+                None => return Err(EncodingError::Invalid(encoding, bom + idx * 4))
+            }
+        }
+    }
+    Ok(Cow::Owned(utf8))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::encoding::{detect, to_utf8, Encoding, EncodingError};
+
+    const JSON: &str = r#"{"name": "zoë 🎸"}"#;
+
+    fn utf16(text: &str, little: bool, bom: bool) -> Vec<u8> {
+        let bom: Option<u16> = if bom { Some(0xFEFF) } else { None };
+        bom.into_iter().chain(text.encode_utf16())
+            .flat_map(|unit| if little { unit.to_le_bytes() } else { unit.to_be_bytes() })
+            .collect()
+    }
+
+    fn utf32(text: &str, little: bool) -> Vec<u8> {
+        text.chars().flat_map(|character| if little { (character as u32).to_le_bytes() } else { (character as u32).to_be_bytes() }).collect()
+    }
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(detect(JSON.as_bytes()), (Encoding::UTF8, 0));
+        assert_eq!(detect(b"\xEF\xBB\xBF{}"), (Encoding::UTF8, 3));
+        assert_eq!(detect(&utf16(JSON, true, true)), (Encoding::UTF16LE, 2));
+        assert_eq!(detect(&utf16(JSON, false, false)), (Encoding::UTF16BE, 0));
+        assert_eq!(detect(&utf16("1", true, false)), (Encoding::UTF16LE, 0));
+        assert_eq!(detect(&utf32(JSON, true)), (Encoding::UTF32LE, 0));
+        assert_eq!(detect(&utf32(JSON, false)), (Encoding::UTF32BE, 0));
+        assert_eq!(detect(b""), (Encoding::UTF8, 0));
+    }
+
+    #[test]
+    fn test_to_utf8() {
+        for encoded in [utf16(JSON, true, true), utf16(JSON, false, false), utf16(JSON, true, false), utf32(JSON, true), utf32(JSON, false)] {
+            assert_eq!(to_utf8(&encoded).unwrap().as_ref(), JSON.as_bytes());
+        }
+        assert_eq!(to_utf8(b"\xEF\xBB\xBF[1]").unwrap().as_ref(), b"[1]");
+        let mut truncated: Vec<u8> = utf16(JSON, true, true);
+        truncated.pop();
+        assert_eq!(to_utf8(&truncated), Err(EncodingError::Truncated(Encoding::UTF16LE)));
+        // a lone high surrogate
+        assert_eq!(to_utf8(&[0xFF, 0xFE, b'"', 0, 0x00, 0xD8, b'"', 0]), Err(EncodingError::Invalid(Encoding::UTF16LE, 4)));
+        // a surrogate pair before the lone surrogate counts as two units
+        let mut astral: Vec<u8> = utf16("\"🎸🎸", false, true);
+        astral.extend_from_slice(&[0xDC, 0x00, 0, b'"']);
+        assert_eq!(to_utf8(&astral), Err(EncodingError::Invalid(Encoding::UTF16BE, 12)));
+    }
+}
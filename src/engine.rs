@@ -1,34 +1,312 @@
-use crate::reader::{JSONReader, Reader, JSONValue, JSONType};
+use std::borrow::Cow;
+use crate::duplicates::DuplicateKeys;
+use crate::encoding;
+use crate::lenient;
+use crate::parser::ParseError;
+use crate::reader::{JSONRange, JSONReader, Reader, JSONValue, JSONType};
 use crate::query::Query;
+use crate::string::{self, KeyMatch};
+use crate::structural::StructuralIndex;
+use crate::suggest;
 use crate::validation::Validator;
 
+/// # Safety
+/// [raw] must be valid UTF-8
 pub unsafe fn extract(json: &[u8], raw: &[u8]) {
+    let json: Cow<[u8]> = match encoding::to_utf8(json) {
+        Ok(json) => json,
+        Err(error) => return println!("unreadable input: {}", error)
+    };
+    let json: &[u8] = &json;
     let mut reader: JSONReader = JSONReader::new(json);
     let query: Query = Query::from(std::str::from_utf8_unchecked(raw));
-    for (idx, selector) in query.components.iter().enumerate() {
+    if !query.filters.is_empty() {
+        for value in select(json, &query) {
+            match value.json_type {
+                JSONType::STRING => print_string(json, &value),
+                _ => reader.print_at(value.range.0, value.range.1)
+            }
+        }
+        return
+    }
+    let mut level: usize = 0;
+    for selector in query.components.iter() {
         println!("performing selector: {}", std::str::from_utf8_unchecked(selector.path));
         match reader.find_key(selector.path) {
-            None => return,
+            None => {
+                println!("key not found: {}", String::from_utf8_lossy(selector.path));
+                reader.seek(level);
+                let suggestions: Vec<String> = did_you_mean(json, &mut reader, selector.path).iter()
+                    .map(|key| String::from_utf8_lossy(key).into_owned())
+                    .collect();
+                if !suggestions.is_empty() {
+                    println!("did you mean: {}?", suggestions.join(", "));
+                }
+                return
+            },
             Some((start, end)) => {
                 reader.print_at(start, end);
-                let val: JSONValue = reader.read_value().unwrap();
+                let val: JSONValue = match reader.read_value() {
+                    None => return println!("no value for key: {}", String::from_utf8_lossy(selector.path)),
+                    Some(val) => val
+                };
                 match val.json_type {
-                    JSONType::STRING => reader.print_at(val.range.0, val.range.1),
+                    JSONType::STRING => print_string(json, &val),
                     JSONType::OBJECT | JSONType::ARRAY => {
                         reader.print_at(val.range.0, val.range.1);
                         reader.seek(val.range.0);
+                        level = val.range.0;
                     },
-                    JSONType::BOOLEAN => {
-                        reader.print_at(val.range.0, val.range.1);
+                    JSONType::BOOLEAN | JSONType::NUMBER | JSONType::NULL => match scalar(&mut reader, &val) {
+                        None => println!("no value for key: {}", String::from_utf8_lossy(selector.path)),
+                        Some(text) => println!("printing: '{}'", text)
                     }
-                    _ => println!("LOL WUT")
                 }
             }
         };
     }
 }
 
-pub fn validate(json :&[u8]) {
+/// A number, boolean or null as written in the document
+fn scalar<R: Reader>(reader: &mut R, value: &JSONValue) -> Option<String> {
+    reader.select(value.range).map(|bytes| String::from_utf8_lossy(bytes).into_owned())
+}
+
+/// Prints a string value with its escapes resolved
+fn print_string(json: &[u8], value: &JSONValue) {
+    match json.get(value.range.0..value.range.1).map(string::decode) {
+        Some(Ok(decoded)) => println!("printing: '{}'", decoded),
+        Some(Err(error)) => println!("invalid string: {}", error),
+        None => println!("invalid string: out of bounds")
+    }
+}
+
+/// Resolves the query's path and applies its filters.
+/// Filters on a path that selects an array keep the matching elements of that array.
+pub fn select(json: &[u8], query: &Query) -> Vec<JSONValue> {
+    select_with(json, query, KeyMatch::Exact)
+}
+
+/// [select], comparing keys along the path as [matching] describes
+pub fn select_with(json: &[u8], query: &Query, matching: KeyMatch) -> Vec<JSONValue> {
+    match JSONReader::new(json).read_value() {
+        None => vec!(),
+        // without a policy nothing can fail
+        Some(root) => select_from(json, root, query, matching, None).unwrap_or_default()
+    }
+}
+
+/// [select_with], settling a key that repeats along the path as [duplicates] says.
+/// Keys are compared with their escapes resolved, see [Reader::find_key_matching].
+pub fn select_with_policy(json: &[u8], query: &Query, matching: KeyMatch, duplicates: DuplicateKeys) -> Result<Vec<JSONValue>, ParseError> {
+    match JSONReader::new(json).read_value() {
+        None => Ok(vec!()),
+        Some(root) => select_from(json, root, query, matching, Some(duplicates))
+    }
+}
+
+/// [select] for a JSONC or JSON5 document, read by converting it with [lenient::to_strict].
+/// The values index into the strict document returned with them, not into [json].
+pub fn select_lenient(json: &[u8], query: &Query) -> Result<(Box<[u8]>, Vec<JSONValue>), ParseError> {
+    let strict: Box<[u8]> = lenient::to_strict(json)?;
+    let values: Vec<JSONValue> = select(&strict, query);
+    Ok((strict, values))
+}
+
+/// Runs [query] against every document of a stream of concatenated documents
+pub fn select_documents(json: &[u8], query: &Query) -> Vec<Vec<JSONValue>> {
+    JSONReader::new(json).documents()
+        .map(|root| select_from(json, root, query, KeyMatch::Exact, None).unwrap_or_default())
+        .collect()
+}
+
+/// The value the query's path leads to in [json], ignoring its filters, with the range
+/// of the key its last step looked up. There is no key for the root or an array element.
+pub(crate) fn resolve(json: &[u8], query: &Query) -> Option<(Option<JSONRange>, JSONValue)> {
+    let root: JSONValue = JSONReader::new(json).read_value()?;
+    resolve_from(json, root, query, KeyMatch::Exact, None).ok()?
+}
+
+/// Follows the query's path starting from the value [root] of [json], see [resolve].
+/// Keys are looked up with [Reader::find_key_matching] when there is a policy to apply.
+fn resolve_from(json: &[u8], root: JSONValue, query: &Query, matching: KeyMatch, duplicates: Option<DuplicateKeys>) -> Result<Option<(Option<JSONRange>, JSONValue)>, ParseError> {
     let mut reader: JSONReader = JSONReader::new(json);
-    println!("valid json?: {}", reader.validate());
-}
\ No newline at end of file
+    let mut key: Option<JSONRange> = None;
+    let mut value: JSONValue = root;
+    for selector in query.components.iter().filter(|selector| !selector.path.is_empty()) {
+        reader.seek(value.range.0);
+        let next: Option<(Option<JSONRange>, JSONValue)> = match (value.json_type, selector.index()) {
+            (JSONType::ARRAY, Some(index)) => reader.elements().nth(index).map(|element| (None, element)),
+            (JSONType::OBJECT, None) => {
+                let found: Option<JSONRange> = match duplicates {
+                    None => reader.find_key_with(selector.path, matching),
+                    Some(duplicates) => reader.find_key_matching(selector.path, matching, duplicates)?
+                };
+                found.and_then(|found| reader.read_value().map(|member| (Some(found), member)))
+            },
+            _ => None
+        };
+        (key, value) = match next {
+            None => return Ok(None),
+            Some(next) => next
+        };
+    }
+    Ok(Some((key, value)))
+}
+
+/// Resolves [query] starting from the value [root] of [json]
+fn select_from(json: &[u8], root: JSONValue, query: &Query, matching: KeyMatch, duplicates: Option<DuplicateKeys>) -> Result<Vec<JSONValue>, ParseError> {
+    let value: JSONValue = match resolve_from(json, root, query, matching, duplicates)? {
+        None => return Ok(vec!()),
+        Some((_, value)) => value
+    };
+    if query.filters.is_empty() {
+        return Ok(vec!(value))
+    }
+    let mut reader: JSONReader = JSONReader::new(json);
+    let candidates: Vec<JSONValue> = match value.json_type {
+        JSONType::ARRAY => {
+            reader.seek(value.range.0);
+            reader.elements().collect()
+        },
+        _ => vec!(value)
+    };
+    Ok(candidates.into_iter().filter(|candidate| query.keeps(candidate.json_type)).collect())
+}
+
+/// [select], hopping through a prebuilt [StructuralIndex] of [json] instead of reading it byte by byte
+pub fn select_indexed(json: &[u8], index: &StructuralIndex, query: &Query) -> Vec<JSONValue> {
+    let mut value: JSONValue = match index.root(json) {
+        None => return vec!(),
+        Some(value) => value
+    };
+    for selector in query.components.iter().filter(|selector| !selector.path.is_empty()) {
+        let next: Option<JSONValue> = match (value.json_type, selector.index()) {
+            (JSONType::ARRAY, Some(position)) => index.element(json, value.range.0, position),
+            (JSONType::OBJECT, None) => index.find_key(json, value.range.0, selector.path),
+            _ => None
+        };
+        value = match next {
+            None => return vec!(),
+            Some(value) => value
+        };
+    }
+    if query.filters.is_empty() {
+        return vec!(value)
+    }
+    let candidates: Vec<JSONValue> = match value.json_type {
+        JSONType::ARRAY => index.elements(json, value.range.0),
+        _ => vec!(value)
+    };
+    candidates.into_iter().filter(|candidate| query.keeps(candidate.json_type)).collect()
+}
+
+/// Suggests keys in the reader's current level that are likely typos of [key], nearest first
+pub fn did_you_mean<'a>(json: &'a [u8], reader: &mut JSONReader, key: &[u8]) -> Vec<&'a [u8]> {
+    let keys: Vec<&'a [u8]> = reader.keys().into_iter()
+        .filter_map(|(start, end)| json.get(start..end))
+        .collect();
+    suggest::closest(key, &keys)
+}
+
+pub fn validate(json :&[u8]) {
+    let json: Cow<[u8]> = match encoding::to_utf8(json) {
+        Ok(json) => json,
+        Err(error) => return println!("valid json?: false, {}", error)
+    };
+    let mut reader: JSONReader = JSONReader::new(&json);
+    match reader.check() {
+        Ok(()) => println!("valid json?: true"),
+        Err(error) => println!("valid json?: false, {}", error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::duplicates::DuplicateKeys;
+    use crate::engine::{did_you_mean, scalar, select, select_documents, select_indexed, select_lenient, select_with_policy};
+    use crate::parser::{ErrorKind, ParseError};
+    use crate::query::Query;
+    use crate::reader::{JSONReader, JSONType, JSONValue, Reader};
+    use crate::string::KeyMatch;
+    use crate::structural::StructuralIndex;
+
+    const JSON: &[u8] = r#"{"name":"alex","hobbies":["cooking","guitar"],"nested":{"foo":"bar","herp":false}}"#.as_bytes();
+
+    #[test]
+    fn test_scalar() {
+        let json: &[u8] = br#"{"age": -32.5e1, "none": null, "ok": true}"#;
+        for (key, expected) in [("age", "-32.5e1"), ("none", "null"), ("ok", "true")] {
+            let value: JSONValue = select(json, &Query::from(key))[0];
+            assert_eq!(scalar(&mut JSONReader::new(json), &value), Some(expected.to_string()));
+        }
+    }
+
+    #[test]
+    fn test_did_you_mean() {
+        let mut reader: JSONReader = JSONReader::new(JSON);
+        assert_eq!(did_you_mean(JSON, &mut reader, b"hobbys"), vec!(b"hobbies".as_ref()));
+    }
+
+    #[test]
+    fn test_did_you_mean_nested() {
+        let mut reader: JSONReader = JSONReader::new(JSON);
+        reader.seek(55);
+        assert_eq!(did_you_mean(JSON, &mut reader, b"hurp"), vec!(b"herp".as_ref()));
+    }
+
+    #[test]
+    fn test_select_filters() {
+        let json: &[u8] = r#"{"mixed": ["a", 1, null, {"b": 2}, "c", [true]], "name": "alex"}"#.as_bytes();
+        let strings: Vec<&[u8]> = select(json, &Query::from("mixed | strings")).into_iter()
+            .map(|value| &json[value.range.0..value.range.1])
+            .collect();
+        assert_eq!(strings, vec!(b"a".as_ref(), b"c"));
+        let scalars: Vec<JSONType> = select(json, &Query::from("mixed | scalars")).into_iter()
+            .map(|value| value.json_type)
+            .collect();
+        assert_eq!(scalars, vec!(JSONType::STRING, JSONType::NUMBER, JSONType::NULL, JSONType::STRING));
+        assert_eq!(select(json, &Query::from("name | strings")).len(), 1);
+        assert_eq!(select(json, &Query::from("mixed.[3] | objects")).len(), 1);
+        assert!(select(json, &Query::from("name | numbers")).is_empty());
+    }
+
+    #[test]
+    fn test_select_with_policy() {
+        let json: &[u8] = br#"{"a": {"id": 1}, "\u0061": {"id": 2}}"#;
+        let id = |duplicates: DuplicateKeys| select_with_policy(json, &Query::from("a.id"), KeyMatch::Exact, duplicates)
+            .map(|values| values.iter().map(|value| &json[value.range.0..value.range.1]).collect::<Vec<&[u8]>>());
+        assert_eq!(id(DuplicateKeys::FirstWins), Ok(vec!(b"1".as_ref())));
+        assert_eq!(id(DuplicateKeys::LastWins), Ok(vec!(b"2".as_ref())));
+        assert_eq!(id(DuplicateKeys::Error), Err(ParseError{ kind: ErrorKind::DuplicateKey(2), offset: 18 }));
+        assert_eq!(select_with_policy(json, &Query::from("b"), KeyMatch::Exact, DuplicateKeys::Error), Ok(vec!()));
+    }
+
+    #[test]
+    fn test_select_lenient() {
+        let json: &[u8] = b"// settings\n{hobbies: ['cooking', 'guitar',], /* soon */}";
+        let (strict, values): (Box<[u8]>, Vec<JSONValue>) = select_lenient(json, &Query::from("hobbies.[1]")).unwrap();
+        assert_eq!(values.iter().map(|value| &strict[value.range.0..value.range.1]).collect::<Vec<&[u8]>>(), vec!(b"guitar".as_ref()));
+        assert!(select_lenient(b"{a: }", &Query::from("a")).is_err());
+    }
+
+    #[test]
+    fn test_select_indexed() {
+        let json: &[u8] = r#"{"mixed": ["a", 1, null, {"b": 2}, "c", [true]], "name": "alex", "n": {"e": {}}}"#.as_bytes();
+        let index: StructuralIndex = StructuralIndex::build(json);
+        for query in ["name", "mixed", "mixed.[3].b", "mixed.[5].[0]", "mixed | scalars", "n.e", "n.x", "mixed.[9]"] {
+            assert_eq!(select_indexed(json, &index, &Query::from(query)), select(json, &Query::from(query)), "{}", query);
+        }
+    }
+
+    #[test]
+    fn test_select_documents() {
+        let json: &[u8] = br#"{"id": 1} 7 {"id": "two"}[{"id": 3}]"#;
+        let ids: Vec<Vec<&[u8]>> = select_documents(json, &Query::from("id")).into_iter()
+            .map(|values| values.into_iter().map(|value| &json[value.range.0..value.range.1]).collect())
+            .collect();
+        assert_eq!(ids, vec!(vec!(b"1".as_ref()), vec!(), vec!(b"two".as_ref()), vec!()));
+        assert_eq!(select(b" 42 ", &Query::from("")).len(), 1);
+        assert_eq!(select(b" 42 ", &Query::from(" | numbers")).len(), 1);
+    }
+}
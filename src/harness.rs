@@ -0,0 +1,266 @@
+//! Runs arbitrary bytes through every public entry point, for the fuzz targets under
+//! `fuzz/` and the regression corpus that runs with `cargo test`.
+//!
+//! Results are hardly checked: malformed input must come back as an error or an empty
+//! answer, and the failure looked for is a panic.
+
+use std::fs;
+use std::io::Cursor;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use bytes::Bytes;
+use crate::duplicates::DuplicateKeys;
+use crate::encoding;
+use crate::engine;
+use crate::lazy::LazyValue;
+use crate::lenient;
+use crate::limits::Limits;
+use crate::minify;
+use crate::mmap::MappedFile;
+use crate::patch;
+use crate::path;
+use crate::ndjson::{self, OnError};
+use crate::query::Query;
+use crate::reader::{JSONReader, JSONValue, Reader};
+use crate::result::Value;
+use crate::shared::BytesReader;
+use crate::sidecar::{self, IndexedFile};
+use crate::sourcemap;
+use crate::stream::StreamReader;
+use crate::string::{self, KeyMatch};
+use crate::structural::StructuralIndex;
+use crate::tape::Tape;
+use crate::typed::Number;
+use crate::validation::{self, Validator};
+
+const FOREIGN: &[u8] = b"[1]";
+
+/// Splits [data] into a query and a document at the first newline, so one input
+/// exercises both the query syntax and the readers
+pub fn split(data: &[u8]) -> (&str, &[u8]) {
+    match data.iter().position(|byte| *byte == b'\n') {
+        Some(idx) => match std::str::from_utf8(&data[..idx]) {
+            Ok(query) => (query, &data[idx + 1..]),
+            Err(_) => ("", data)
+        },
+        None => ("", data)
+    }
+}
+
+/// Feeds [data] to everything that takes a document
+pub fn run(data: &[u8]) {
+    let (raw, json): (&str, &[u8]) = split(data);
+    let query: Query = Query::from(raw);
+
+    validate(json);
+    read(json, &query);
+    select(json, &query);
+    convert(json);
+    index(json, &query);
+    values(json, &query);
+
+    let key: &[u8] = raw.as_bytes();
+    let _ = string::decode(json);
+    for matching in [KeyMatch::Exact, KeyMatch::Decoded, KeyMatch::Normalized, KeyMatch::CaseInsensitive] {
+        matching.matches(json, key);
+    }
+    validation::is_number(json);
+    lenient::is_number(json);
+    if let Ok(number) = Number::parse(json) {
+        let _ = (number.as_i64(), number.as_u64(), number.as_i128(), number.as_u128(), number.as_f64());
+        if let Ok(decimal) = number.as_decimal() {
+            let _ = (decimal.to_i128(), decimal.to_u128(), decimal.to_string());
+        }
+    }
+}
+
+fn validate(json: &[u8]) {
+    let _ = JSONReader::new(json).check();
+    let _ = JSONReader::new(json).check_concatenated();
+    let _ = JSONReader::new(json).check_lenient();
+    let _ = JSONReader::new(json).check_unique_keys();
+    let _ = JSONReader::new(json).check_with_limits(Limits{ max_depth: 4, max_array_length: 4, ..Limits::default() });
+    let _ = ndjson::check(json);
+}
+
+/// The low level reads, started from every offset of the document
+fn read(json: &[u8], query: &Query) {
+    for offset in 0..=json.len().min(64) {
+        let mut reader: JSONReader = JSONReader::new(json);
+        reader.seek(offset);
+        for read in [Reader::read_string, Reader::read_number, Reader::read_json] {
+            reader.seek(offset);
+            if let Some(range) = read(&mut reader) {
+                reader.select(range);
+                reader.print_at(json.len() + 1, 0);
+            }
+        }
+        reader.seek(offset);
+        reader.read_known(4);
+        reader.seek(offset);
+        reader.keys();
+        reader.seek(offset);
+        if let Some(selector) = query.components.first() {
+            reader.find_key(selector.path);
+            reader.seek(offset);
+            let _ = reader.find_key_with_policy(selector.path, DuplicateKeys::LastWins);
+            reader.seek(offset);
+            engine::did_you_mean(json, &mut reader, selector.path);
+        }
+        reader.seek(offset);
+        reader.members().count();
+        reader.elements().count();
+        reader.documents().count();
+        reader.seek(offset);
+        Value::read(&mut reader);
+    }
+}
+
+fn select(json: &[u8], query: &Query) {
+    for matching in [KeyMatch::Exact, KeyMatch::Decoded, KeyMatch::Normalized, KeyMatch::CaseInsensitive] {
+        engine::select_with(json, query, matching);
+        let _ = engine::select_with_policy(json, query, matching, DuplicateKeys::Error);
+    }
+    engine::select_documents(json, query);
+    let _ = engine::select_lenient(json, query);
+    sourcemap::locate(json, query);
+    for offset in [0, json.len() / 2, json.len()] {
+        let _ = path::path_at(json, offset).map(|found| found.map(|found| found.to_query()));
+    }
+    let _ = ndjson::select(json, query, OnError::Skip);
+
+    let shared: BytesReader = BytesReader::new(Bytes::copy_from_slice(json));
+    shared.select(query);
+    StreamReader::with_chunk_size(Cursor::new(json), 3).query(query);
+}
+
+fn convert(json: &[u8]) {
+    minify::minify(json);
+    let _ = lenient::to_strict(json);
+    let _ = ndjson::minify(json, OnError::Skip);
+    let _ = encoding::to_utf8(json);
+    let _ = patch::set(json, &Query::from("a.[0]"), b"1");
+    if let Ok(patched) = patch::set(b"[0]", &Query::from("[0]"), json) {
+        // a replacement that validates on its own must leave a valid document
+        assert_eq!(JSONReader::new(&patched).check_with_limits(Limits::unlimited()), Ok(()));
+    }
+}
+
+fn index(json: &[u8], query: &Query) {
+    let structural: StructuralIndex = StructuralIndex::build(json);
+    engine::select_indexed(json, &structural, query);
+    for &position in structural.positions().iter().take(64) {
+        structural.find_key(json, position, b"a");
+        structural.elements(json, position);
+        structural.element(json, position, 1);
+    }
+
+    if let Ok(tape) = Tape::build(json) {
+        tape.select(json, query);
+        for entry in 0..tape.len().min(64) {
+            tape.find_key(json, entry, b"a");
+            let _ = tape.find_key_with_policy(json, entry, b"a", DuplicateKeys::Error);
+            tape.children(entry);
+            tape.element(entry, 1);
+        }
+        sidecar::decode(&sidecar::encode(&tape, json), json);
+    }
+    // the document read as a sidecar of itself
+    sidecar::decode(json, json);
+}
+
+fn values(json: &[u8], query: &Query) {
+    Value::parse_with(json, Limits::default(), DuplicateKeys::LastWins);
+    Value::parse_lenient(json);
+    if let Some(value) = Value::parse(json) {
+        value.select(query);
+        value.to_bytes();
+    }
+    if let Some(lazy) = LazyValue::parse(json) {
+        lazy.get("a");
+        let _ = lazy.get_with_policy("a", DuplicateKeys::LastWins);
+        lazy.index(0);
+        lazy.keys().count();
+    }
+    for value in engine::select(json, query) {
+        typed(json, value);
+        foreign(value);
+    }
+}
+
+/// A small document mapped once for the whole run, to hand values from other documents to
+fn mapped() -> Option<&'static (MappedFile, IndexedFile)> {
+    static FILES: OnceLock<Option<(MappedFile, IndexedFile)>> = OnceLock::new();
+    FILES.get_or_init(|| {
+        let path: PathBuf = std::env::temp_dir().join(format!("gsjf-harness-{}.json", std::process::id()));
+        fs::write(&path, FOREIGN).ok()?;
+        let files: Option<(MappedFile, IndexedFile)> = MappedFile::open(&path).ok().zip(IndexedFile::open(&path).ok());
+        let _ = fs::remove_file(sidecar::sidecar_path(&path));
+        let _ = fs::remove_file(&path);
+        files
+    }).as_ref()
+}
+
+/// Slices a document [value] was not read from
+fn foreign(value: JSONValue) {
+    let _ = JSONValue::new(value.json_type(), (0, value.range().1)).extent();
+    let _ = LazyValue::new(FOREIGN, value).as_bytes();
+    let _ = BytesReader::new(Bytes::from_static(FOREIGN)).slice(&value);
+    if let Some((mapped, indexed)) = mapped() {
+        let _ = (mapped.slice(&value), indexed.slice(&value));
+    }
+}
+
+fn typed(json: &[u8], value: JSONValue) {
+    let _ = value.as_str(json);
+    let _ = value.as_bool(json);
+    let _ = (value.as_i64(json), value.as_u64(json), value.as_f64(json), value.as_decimal(json));
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::panic;
+    use std::path::PathBuf;
+    use crate::harness::run;
+
+    /// Seeds and every input that once panicked, one file each
+    fn corpus() -> Vec<(PathBuf, Vec<u8>)> {
+        let dir: PathBuf = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("fuzz").join("corpus").join("run");
+        let mut inputs: Vec<(PathBuf, Vec<u8>)> = fs::read_dir(&dir).unwrap()
+            .map(|entry| entry.unwrap().path())
+            .map(|path| {
+                let bytes: Vec<u8> = fs::read(&path).unwrap();
+                (path, bytes)
+            })
+            .collect();
+        inputs.sort();
+        inputs
+    }
+
+    #[test]
+    fn test_corpus() {
+        for (path, input) in corpus() {
+            assert!(panic::catch_unwind(|| run(&input)).is_ok(), "{} panicked", path.display());
+        }
+    }
+
+    /// A cheap stand-in for the fuzzer: every truncation of every seed, and each seed
+    /// with single bytes swapped for the ones parsers trip over
+    #[test]
+    fn test_mutations() {
+        const BYTES: &[u8] = b"\0\"\\{}[]:,-.e0 \n/*'x\xFF\xEF";
+        for (_, input) in corpus() {
+            for end in 0..input.len() {
+                run(&input[..end]);
+            }
+            for idx in 0..input.len().min(48) {
+                let mut mutated: Vec<u8> = input.clone();
+                for &byte in BYTES {
+                    mutated[idx] = byte;
+                    run(&mutated);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,194 @@
+//! Iterators over the members of an object, the elements of an array and the
+//! documents of a concatenated stream.
+//!
+//! Both work on a copy of the reader, so they never move the reader they were created
+//! from and never allocate. Nested containers are skipped whole with [Reader::scan].
+
+use crate::reader::{JSONRange, JSONReader, JSONValue, Reader};
+
+/// Yields the key range and value of every member of an object
+pub struct Members<'a> {
+    reader: JSONReader<'a>,
+    started: bool,
+    done: bool,
+}
+
+/// Yields every element of an array
+pub struct Elements<'a> {
+    reader: JSONReader<'a>,
+    started: bool,
+    done: bool,
+}
+
+/// Yields every top-level value of a stream of concatenated documents, e.g. `{..}{..}[..]`
+pub struct Documents<'a> {
+    reader: JSONReader<'a>,
+}
+
+impl<'a> JSONReader<'a> {
+    /// Iterates the object the reader is positioned on, i.e. the next token past any
+    /// whitespace is its {. Anything other than an object yields nothing.
+    pub fn members(&self) -> Members<'a> {
+        Members{ reader: self.clone(), started: false, done: false }
+    }
+
+    /// Iterates the array the reader is positioned on, i.e. the next token past any
+    /// whitespace is its [. Anything other than an array yields nothing.
+    pub fn elements(&self) -> Elements<'a> {
+        Elements{ reader: self.clone(), started: false, done: false }
+    }
+
+    /// Iterates the documents from the reader's position to the end of the data.
+    /// Like the other iterators this skips values without validating them.
+    pub fn documents(&self) -> Documents<'a> {
+        Documents{ reader: self.clone() }
+    }
+}
+
+/// Moves the reader onto the next entry of a container, false once there are no more.
+/// The first call consumes the [open] token, later calls consume the separating comma.
+fn advance<R: Reader + ?Sized>(reader: &mut R, started: &mut bool, open: u8, close: u8) -> bool {
+    reader.skip_past_whitespace();
+    if !*started {
+        *started = true;
+        if reader.next() != Some(open) {
+            return false
+        }
+        reader.skip_past_whitespace();
+        if reader.peek() == Some(close) {
+            reader.next();
+            return false
+        }
+        return true
+    }
+    match reader.next() {
+        Some(b',') => {
+            reader.skip_past_whitespace();
+            true
+        },
+        _ => false
+    }
+}
+
+/// Reads the key of the next member of an object, consuming through its colon so the
+/// reader is left just before the member's value. The value must be read or skipped
+/// with [Reader::read_value] before asking for the next key.
+pub(crate) fn next_key<R: Reader + ?Sized>(reader: &mut R, started: &mut bool) -> Option<JSONRange> {
+    if !advance(reader, started, b'{', b'}') || reader.next() != Some(b'"') {
+        return None
+    }
+    let key: JSONRange = reader.read_string()?;
+    reader.skip_past_whitespace();
+    match reader.next() {
+        Some(b':') => Some(key),
+        _ => None
+    }
+}
+
+/// Reads the next element of an array
+pub(crate) fn next_element<R: Reader + ?Sized>(reader: &mut R, started: &mut bool) -> Option<JSONValue> {
+    match advance(reader, started, b'[', b']') {
+        true => reader.read_value(),
+        false => None
+    }
+}
+
+/// Moves the reader to just before the element at [index], false if the array is shorter
+pub(crate) fn skip_elements<R: Reader + ?Sized>(reader: &mut R, index: usize) -> bool {
+    let mut started: bool = false;
+    for _ in 0..index {
+        let position: usize = reader.position();
+        reader.release(position);
+        if next_element(reader, &mut started).is_none() {
+            return false
+        }
+    }
+    advance(reader, &mut started, b'[', b']')
+}
+
+impl Iterator for Members<'_> {
+    type Item = (JSONRange, JSONValue);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None
+        }
+        let member: Option<Self::Item> = next_key(&mut self.reader, &mut self.started)
+            .and_then(|key| self.reader.read_value().map(|value| (key, value)));
+        if member.is_none() {
+            self.done = true;
+        }
+        member
+    }
+}
+
+impl Iterator for Elements<'_> {
+    type Item = JSONValue;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None
+        }
+        let element: Option<JSONValue> = next_element(&mut self.reader, &mut self.started);
+        if element.is_none() {
+            self.done = true;
+        }
+        element
+    }
+}
+
+impl Iterator for Documents<'_> {
+    type Item = JSONValue;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.reader.read_value()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::reader::{JSONReader, JSONType, Reader};
+
+    #[test]
+    fn test_members() {
+        let json: &[u8] = br#" {"a": "}", "b" : {"c": [1, "]"]}, "d":true} "#;
+        let reader: JSONReader = JSONReader::new(json);
+        let members: Vec<(&[u8], &[u8])> = reader.members()
+            .map(|(key, value)| (&json[key.0..key.1], &json[value.range.0..value.range.1]))
+            .collect();
+        assert_eq!(members, vec!(
+            (b"a".as_ref(), b"}".as_ref()),
+            (b"b", br#"{"c": [1, "]"]}"#),
+            (b"d", b"true"),
+        ));
+        assert_eq!(JSONReader::new(b"{ }").members().count(), 0);
+        assert_eq!(JSONReader::new(b"[1]").members().count(), 0);
+    }
+
+    #[test]
+    fn test_elements() {
+        let json: &[u8] = r#"["a", 1, -2.5e3, true, null, {"b": [1]}, []]"#.as_bytes();
+        let elements: Vec<JSONType> = JSONReader::new(json).elements().map(|value| value.json_type).collect();
+        assert_eq!(elements, vec!(JSONType::STRING, JSONType::NUMBER, JSONType::NUMBER, JSONType::BOOLEAN,
+                                  JSONType::NULL, JSONType::OBJECT, JSONType::ARRAY));
+        assert_eq!(JSONReader::new(b"[ ]").elements().count(), 0);
+    }
+
+    #[test]
+    fn test_documents() {
+        let json: &[u8] = br#"{"a": [1]}{"b": 2} [3]"x"  4 null "#;
+        let documents: Vec<&[u8]> = JSONReader::new(json).documents()
+            .map(|value| &json[value.range.0..value.range.1])
+            .collect();
+        assert_eq!(documents, vec!(br#"{"a": [1]}"#.as_ref(), br#"{"b": 2}"#, b"[3]", b"x", b"4", b"null"));
+        assert_eq!(JSONReader::new(b" ").documents().count(), 0);
+    }
+
+    #[test]
+    fn test_does_not_move_reader() {
+        let json: &[u8] = br#"{"a": 1, "b": 2}"#;
+        let mut reader: JSONReader = JSONReader::new(json);
+        assert_eq!(reader.members().count(), 2);
+        assert_eq!(reader.next(), Some(b'{'));
+    }
+}
@@ -0,0 +1,174 @@
+//! A borrowed view of a JSON value that only looks inside containers when asked to.
+//!
+//! The first access to a container's children scans that one level, skipping nested
+//! containers without parsing them, and caches where each child lives. Later lookups
+//! by key or index are then O(1), and each child keeps its own cache.
+
+use std::cell::OnceCell;
+use std::collections::HashMap;
+use crate::duplicates::DuplicateKeys;
+use crate::parser::ParseError;
+use crate::reader::{JSONRange, JSONReader, JSONType, JSONValue, Reader};
+use crate::string::KeyMatch;
+use crate::typed::ValueError;
+
+pub struct LazyValue<'a> {
+    data: &'a [u8],
+    value: JSONValue,
+    children: OnceCell<Children<'a>>,
+}
+
+struct Children<'a> {
+    values: Vec<LazyValue<'a>>,
+    keys: Vec<JSONRange>,
+    /// The first position of each raw key, like [Reader::find_key] finds it
+    lookup: HashMap<&'a [u8], usize>,
+}
+
+impl<'a> LazyValue<'a> {
+    pub fn new(data: &'a [u8], value: JSONValue) -> LazyValue<'a> {
+        LazyValue{ data, value, children: OnceCell::new() }
+    }
+
+    /// Wraps the first value in the document
+    pub fn parse(data: &'a [u8]) -> Option<LazyValue<'a>> {
+        JSONReader::new(data).read_value().map(|value| LazyValue::new(data, value))
+    }
+
+    pub fn json_type(&self) -> JSONType {
+        self.value.json_type
+    }
+
+    pub fn value(&self) -> JSONValue {
+        self.value
+    }
+
+    /// The raw bytes of the value, strings exclude their quotes and are not unescaped.
+    /// Fails if the value was read from some other document.
+    pub fn as_bytes(&self) -> Result<&'a [u8], ValueError> {
+        self.value.bytes(self.data)
+    }
+
+    /// Looks up a key of an object, None for anything else
+    pub fn get(&self, key: &str) -> Option<&LazyValue<'a>> {
+        let children: &Children<'a> = self.children();
+        children.lookup.get(key.as_bytes()).map(|position| &children.values[*position])
+    }
+
+    /// [LazyValue::get], settling a key that repeats as [policy] says. Keys are compared with
+    /// their escapes resolved, so this reads every key rather than the raw key lookup.
+    pub fn get_with_policy(&self, key: &str, policy: DuplicateKeys) -> Result<Option<&LazyValue<'a>>, ParseError> {
+        let children: &Children<'a> = self.children();
+        policy.resolve(children.keys.iter().zip(children.values.iter())
+            .filter(|((start, end), _)| self.data.get(*start..*end).is_some_and(|raw| KeyMatch::Decoded.matches(raw, key.as_bytes())))
+            .map(|((start, _), value)| (*start, value))
+            .collect())
+    }
+
+    /// Looks up an element of an array, or a value of an object by its position
+    pub fn index(&self, index: usize) -> Option<&LazyValue<'a>> {
+        self.children().values.get(index)
+    }
+
+    /// Iterates array elements or object values, scalars have none
+    pub fn iter(&self) -> std::slice::Iter<'_, LazyValue<'a>> {
+        self.children().values.iter()
+    }
+
+    /// Iterates the raw keys of an object in document order
+    pub fn keys(&self) -> impl Iterator<Item = &'a [u8]> + '_ {
+        self.children().keys.iter().map(move |(start, end)| &self.data[*start..*end])
+    }
+
+    pub fn len(&self) -> usize {
+        self.children().values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn children(&self) -> &Children<'a> {
+        self.children.get_or_init(|| self.discover())
+    }
+
+    /// Scans one level of the container, skipping nested containers whole
+    fn discover(&self) -> Children<'a> {
+        let mut children: Children<'a> = Children{ values: vec!(), keys: vec!(), lookup: HashMap::new() };
+        let mut reader: JSONReader = JSONReader::new(self.data);
+        reader.seek(self.value.range.0);
+        match self.value.json_type {
+            JSONType::OBJECT => for ((start, end), value) in reader.members() {
+                let key: &'a [u8] = &self.data[start..end];
+                children.lookup.entry(key).or_insert(children.values.len());
+                children.keys.push((start, end));
+                children.values.push(LazyValue::new(self.data, value));
+            },
+            JSONType::ARRAY => for value in reader.elements() {
+                children.values.push(LazyValue::new(self.data, value));
+            },
+            _ => {}
+        }
+        children
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::duplicates::DuplicateKeys;
+    use crate::lazy::LazyValue;
+    use crate::parser::{ErrorKind, ParseError};
+    use crate::reader::{JSONType, JSONValue};
+    use crate::typed::ValueError;
+
+    const JSON: &[u8] = r#"{"name":"alex","age":32,"hobbies":["cooking","guitar"],"nested":{"foo":"bar","herp":false}}"#.as_bytes();
+
+    #[test]
+    fn test_get() {
+        let root: LazyValue = LazyValue::parse(JSON).unwrap();
+        assert_eq!(root.get("name").unwrap().as_bytes(), Ok(b"alex".as_ref()));
+        assert_eq!(root.get("age").unwrap().json_type(), JSONType::NUMBER);
+        assert_eq!(root.get("nested").unwrap().get("herp").unwrap().as_bytes(), Ok(b"false".as_ref()));
+        assert!(root.get("missing").is_none());
+        assert!(root.get("name").unwrap().get("anything").is_none());
+        let age: JSONValue = root.get("age").unwrap().value();
+        assert_eq!(LazyValue::new(b"{}", age).as_bytes(), Err(ValueError::OutOfBounds));
+    }
+
+    #[test]
+    fn test_get_with_policy() {
+        let root: LazyValue = LazyValue::parse(br#"{"a": 1, "b": 2, "a": 3}"#).unwrap();
+        assert_eq!(root.get("a").unwrap().as_bytes(), Ok(b"1".as_ref()));
+        let found = |policy: DuplicateKeys| root.get_with_policy("a", policy).map(|value| value.map(|value| value.as_bytes().unwrap()));
+        assert_eq!(found(DuplicateKeys::FirstWins), Ok(Some(b"1".as_ref())));
+        assert_eq!(found(DuplicateKeys::LastWins), Ok(Some(b"3".as_ref())));
+        assert_eq!(found(DuplicateKeys::Error), Err(ParseError{ kind: ErrorKind::DuplicateKey(2), offset: 18 }));
+        assert!(root.get_with_policy("c", DuplicateKeys::Error).unwrap().is_none());
+        let root: LazyValue = LazyValue::parse(br#"{"a": 1, "\u0061": 2}"#).unwrap();
+        assert_eq!(root.get_with_policy("a", DuplicateKeys::LastWins).unwrap().unwrap().as_bytes(), Ok(b"2".as_ref()));
+        assert_eq!(root.get_with_policy("a", DuplicateKeys::Error).map(|value| value.is_some()), Err(ParseError{ kind: ErrorKind::DuplicateKey(2), offset: 10 }));
+    }
+
+    #[test]
+    fn test_children_are_lazy_and_cached() {
+        let root: LazyValue = LazyValue::parse(JSON).unwrap();
+        assert!(root.children.get().is_none());
+        let nested: &LazyValue = root.get("nested").unwrap();
+        assert!(nested.children.get().is_none());
+        let foo: *const LazyValue = nested.get("foo").unwrap();
+        assert!(std::ptr::eq(foo, root.get("nested").unwrap().get("foo").unwrap()));
+    }
+
+    #[test]
+    fn test_index_and_iter() {
+        let root: LazyValue = LazyValue::parse(JSON).unwrap();
+        let hobbies: &LazyValue = root.get("hobbies").unwrap();
+        assert_eq!(hobbies.index(1).unwrap().as_bytes(), Ok(b"guitar".as_ref()));
+        assert!(hobbies.index(2).is_none());
+        let all: Vec<&[u8]> = hobbies.iter().map(|hobby| hobby.as_bytes().unwrap()).collect();
+        assert_eq!(all, vec!(b"cooking".as_ref(), b"guitar"));
+        let keys: Vec<&[u8]> = root.keys().collect();
+        assert_eq!(keys, vec!(b"name".as_ref(), b"age", b"hobbies", b"nested"));
+        assert_eq!(LazyValue::parse(b"[ ]").unwrap().len(), 0);
+    }
+}
@@ -0,0 +1,314 @@
+//! The JSONC and JSON5 extensions accepted by [Parser::lenient], and a converter from
+//! them to strict JSON.
+//!
+//! Supported on top of JSON: `//` and `/* */` comments, trailing commas in objects and
+//! arrays, single quoted strings, the JSON5 escapes `\'` `\v` `\0` `\xHH` and escaped
+//! line breaks, unquoted identifier keys, and JSON5 numbers: hexadecimal, a leading +,
+//! a leading or trailing decimal point, Infinity and NaN. Identifiers follow Unicode's
+//! XID_Start and XID_Continue like ECMAScript's, so `café` and `名前` are keys too.
+
+use crate::minify;
+use crate::parser::{ErrorKind, Event, ParseError, Parser, Token};
+use crate::reader::{JSONReader, JSONType, Reader};
+
+/// Whether [character] can start an unquoted key
+pub(crate) fn is_identifier_start(character: char) -> bool {
+    character == '$' || character == '_' || unicode_ident::is_xid_start(character)
+}
+
+/// Whether [character] can continue an unquoted key, which includes the zero width joiners
+pub(crate) fn is_identifier_part(character: char) -> bool {
+    character == '$' || character == '\u{200C}' || character == '\u{200D}' || unicode_ident::is_xid_continue(character)
+}
+
+/// Decodes the UTF-8 character whose first byte [first] has just been read, consuming
+/// the rest of it. None if the bytes do not form a character.
+pub(crate) fn read_char<R: Reader + ?Sized>(reader: &mut R, first: u8) -> Option<char> {
+    let width: usize = match first {
+        0x00..=0x7F => 1,
+        0xC2..=0xDF => 2,
+        0xE0..=0xEF => 3,
+        0xF0..=0xF4 => 4,
+        _ => return None
+    };
+    let mut bytes: [u8; 4] = [first, 0, 0, 0];
+    for byte in bytes.iter_mut().take(width).skip(1) {
+        *byte = reader.next()?;
+    }
+    std::str::from_utf8(&bytes[..width]).ok()?.chars().next()
+}
+
+/// Assumes the next token is a /, consumes the comment it starts.
+/// False if it does not start a comment or a block comment is never closed.
+pub(crate) fn skip_comment<R: Reader + ?Sized>(reader: &mut R) -> bool {
+    let start: usize = reader.offset();
+    reader.next();
+    match reader.next() {
+        Some(b'/') => {
+            while let Some(byte) = reader.next() {
+                if byte == b'\n' {
+                    break
+                }
+            }
+            true
+        },
+        Some(b'*') => {
+            while let Some(byte) = reader.next() {
+                if byte == b'*' && reader.peek() == Some(b'/') {
+                    reader.next();
+                    return true
+                }
+            }
+            false
+        },
+        _ => {
+            reader.seek(start); // leave the / to be reported
+            false
+        }
+    }
+}
+
+fn hex_digits<R: Reader + ?Sized>(reader: &mut R, count: usize) -> bool {
+    (0..count).all(|_| reader.next().is_some_and(|digit| digit.is_ascii_hexdigit()))
+}
+
+/// Like [crate::validation::Validator::validate_string] for a string opened with [quote],
+/// also allowing the JSON5 escapes
+pub(crate) fn validate_string<R: Reader + ?Sized>(reader: &mut R, quote: u8) -> bool {
+    while let Some(token) = reader.next() {
+        match token {
+            _ if token == quote => return true,
+            b'\\' => {
+                let valid: bool = match reader.next() {
+                    Some(b'"') | Some(b'\'') | Some(b'\\') | Some(b'/') | Some(b'b') | Some(b'f') | Some(b'n')
+                    | Some(b'r') | Some(b't') | Some(b'v') | Some(b'0') | Some(b'\n') => true,
+                    Some(b'\r') => {
+                        if reader.peek() == Some(b'\n') {
+                            reader.next();
+                        }
+                        true
+                    },
+                    // U+2028 and U+2029, the other line terminators
+                    Some(0xE2) => reader.next() == Some(0x80) && matches!(reader.next(), Some(0xA8) | Some(0xA9)),
+                    Some(b'x') => hex_digits(reader, 2),
+                    Some(b'u') => hex_digits(reader, 4),
+                    _ => false
+                };
+                if !valid {
+                    return false
+                }
+            },
+            0x00..=0x1F => return false,
+            _ => {}
+        }
+    }
+    false // never terminated
+}
+
+/// Assumes the first byte of the number has been read
+pub(crate) fn validate_number<R: Reader + ?Sized>(reader: &mut R) -> bool {
+    let start: usize = reader.position();
+    while reader.peek().is_some_and(|byte| byte.is_ascii_alphanumeric() || matches!(byte, b'+' | b'-' | b'.')) {
+        reader.next();
+    }
+    let end: usize = reader.offset();
+    reader.select((start, end)).is_some_and(is_number)
+}
+
+/// Whether [lexeme] is a JSON5 number
+pub fn is_number(lexeme: &[u8]) -> bool {
+    let unsigned: &[u8] = match lexeme.first() {
+        Some(b'+') | Some(b'-') => &lexeme[1..],
+        _ => lexeme
+    };
+    if unsigned == b"Infinity" || unsigned == b"NaN" {
+        return true
+    }
+    if let Some(hex) = unsigned.strip_prefix(b"0x").or_else(|| unsigned.strip_prefix(b"0X")) {
+        return !hex.is_empty() && hex.iter().all(u8::is_ascii_hexdigit)
+    }
+    let (mantissa, exponent): (&[u8], Option<&[u8]>) = split_exponent(unsigned);
+    let (integer, fraction): (&[u8], Option<&[u8]>) = split_fraction(mantissa);
+    let digits = |part: &[u8]| part.iter().all(u8::is_ascii_digit);
+    let exponent_valid: bool = match exponent {
+        None => true,
+        Some(exponent) => {
+            let exponent: &[u8] = exponent.strip_prefix(b"+").or_else(|| exponent.strip_prefix(b"-")).unwrap_or(exponent);
+            !exponent.is_empty() && digits(exponent)
+        }
+    };
+    let fraction: &[u8] = fraction.unwrap_or(b"");
+    digits(integer) && digits(fraction) && exponent_valid
+        && !(integer.is_empty() && fraction.is_empty())
+        && !(integer.len() > 1 && integer[0] == b'0')
+}
+
+fn split_exponent(number: &[u8]) -> (&[u8], Option<&[u8]>) {
+    match number.iter().position(|byte| matches!(byte, b'e' | b'E')) {
+        Some(idx) => (&number[..idx], Some(&number[idx + 1..])),
+        None => (number, None)
+    }
+}
+
+fn split_fraction(mantissa: &[u8]) -> (&[u8], Option<&[u8]>) {
+    match mantissa.iter().position(|byte| *byte == b'.') {
+        Some(idx) => (&mantissa[..idx], Some(&mantissa[idx + 1..])),
+        None => (mantissa, None)
+    }
+}
+
+/// Converts a lenient document to minified strict JSON.
+/// Fails on invalid input, and on Infinity, NaN or hexadecimal numbers past u128 which JSON cannot express.
+pub fn to_strict(json: &[u8]) -> Result<Box<[u8]>, ParseError> {
+    let mut reader: JSONReader = JSONReader::new(json);
+    minify::emit(json, Parser::lenient(&mut reader), |out: &mut Vec<u8>, token: &Token| {
+        let bytes: &[u8] = &json[token.range.0..token.range.1];
+        let quote: Option<u8> = token.range.0.checked_sub(1).map(|idx| json[idx]);
+        match (token.event, quote) {
+            (Event::Key, Some(b'"')) | (Event::Key, Some(b'\'')) | (Event::Value(JSONType::STRING), _) => write_string(out, bytes),
+            (Event::Key, _) => {
+                out.push(b'"');
+                out.extend_from_slice(bytes);
+                out.push(b'"');
+            },
+            (Event::Value(JSONType::NUMBER), _) => match write_number(out, bytes) {
+                true => {},
+                false => return Err(ParseError{ kind: ErrorKind::InvalidNumber, offset: token.range.0 })
+            },
+            _ => out.extend_from_slice(bytes)
+        }
+        Ok(())
+    })
+}
+
+/// Re-escapes the validated contents of a string of either quote
+fn write_string(out: &mut Vec<u8>, contents: &[u8]) {
+    out.push(b'"');
+    let mut idx: usize = 0;
+    while idx < contents.len() {
+        match contents[idx] {
+            b'"' => out.extend_from_slice(b"\\\""),
+            b'\\' => {
+                let escape: u8 = contents[idx + 1];
+                idx += 2;
+                match escape {
+                    b'\'' => out.push(b'\''),
+                    b'0' => out.extend_from_slice(b"\\u0000"),
+                    b'v' => out.extend_from_slice(b"\\u000b"),
+                    b'x' => {
+                        out.extend_from_slice(b"\\u00");
+                        out.extend_from_slice(&contents[idx..idx + 2]);
+                        idx += 2;
+                    },
+                    b'\r' if contents.get(idx) == Some(&b'\n') => idx += 1,
+                    b'\n' | b'\r' => {},
+                    0xE2 => idx += 2,
+                    _ => {
+                        out.push(b'\\');
+                        out.push(escape);
+                    }
+                }
+                continue
+            },
+            byte => out.push(byte)
+        }
+        idx += 1;
+    }
+    out.push(b'"');
+}
+
+/// Writes a validated JSON5 number as JSON, false if JSON has no way to express it
+fn write_number(out: &mut Vec<u8>, lexeme: &[u8]) -> bool {
+    let (negative, unsigned): (bool, &[u8]) = match lexeme.first() {
+        Some(b'-') => (true, &lexeme[1..]),
+        Some(b'+') => (false, &lexeme[1..]),
+        _ => (false, lexeme)
+    };
+    if negative {
+        out.push(b'-');
+    }
+    if let Some(hex) = unsigned.strip_prefix(b"0x").or_else(|| unsigned.strip_prefix(b"0X")) {
+        let value: Option<u128> = std::str::from_utf8(hex).ok().and_then(|hex| u128::from_str_radix(hex, 16).ok());
+        return match value {
+            Some(value) => {
+                out.extend_from_slice(value.to_string().as_bytes());
+                true
+            },
+            None => false
+        }
+    }
+    if unsigned == b"Infinity" || unsigned == b"NaN" {
+        return false
+    }
+    let (mantissa, exponent): (&[u8], Option<&[u8]>) = split_exponent(unsigned);
+    let (integer, fraction): (&[u8], Option<&[u8]>) = split_fraction(mantissa);
+    out.extend_from_slice(if integer.is_empty() { b"0" } else { integer });
+    if let Some(fraction) = fraction.filter(|fraction| !fraction.is_empty()) {
+        out.push(b'.');
+        out.extend_from_slice(fraction);
+    }
+    if let Some(exponent) = exponent {
+        out.push(b'e');
+        out.extend_from_slice(exponent);
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lenient::{is_number, to_strict};
+    use crate::parser::{ErrorKind, ParseError};
+    use crate::reader::JSONReader;
+    use crate::validation::Validator;
+
+    const CONFIG: &[u8] = br#"
+// service settings
+{
+    name: 'gs\'jf "v1"', /* inline */ port: 0x1F90,
+    ratio: .5, scale: +2., limits: [1, 2, 3,],
+    "nested": { $id: 'a\x41\
+b', },
+}
+"#;
+
+    #[test]
+    fn test_to_strict() {
+        let strict: Box<[u8]> = to_strict(CONFIG).unwrap();
+        assert_eq!(std::str::from_utf8(&strict).unwrap(),
+                   r#"{"name":"gs'jf \"v1\"","port":8080,"ratio":0.5,"scale":2,"limits":[1,2,3],"nested":{"$id":"a\u0041b"}}"#);
+        assert!(JSONReader::new(&strict).validate());
+        assert_eq!(to_strict(b"[Infinity]").unwrap_err(), ParseError{ kind: ErrorKind::InvalidNumber, offset: 1 });
+    }
+
+    #[test]
+    fn test_check_lenient() {
+        assert!(JSONReader::new(CONFIG).check_lenient().is_ok());
+        assert!(JSONReader::new(CONFIG).check().is_err());
+        assert_eq!(JSONReader::new(b"[1 /* open").check_lenient(), Err(ParseError{ kind: ErrorKind::UnexpectedEnd, offset: 3 }));
+        assert_eq!(JSONReader::new(b"[1 / 2]").check_lenient(), Err(ParseError{ kind: ErrorKind::UnexpectedToken(b'/'), offset: 3 }));
+        assert!(JSONReader::new(b"[1,,]").check_lenient().is_err());
+        assert!(JSONReader::new(br#"{"a": }"#).check_lenient().is_err());
+        assert!(JSONReader::new(b"{1a: 1}").check_lenient().is_err());
+    }
+
+    #[test]
+    fn test_identifiers() {
+        let strict: Box<[u8]> = to_strict("{café: 1, 名前: 2, _a$\u{200D}b: 3}".as_bytes()).unwrap();
+        assert_eq!(std::str::from_utf8(&strict).unwrap(), "{\"café\":1,\"名前\":2,\"_a$\u{200D}b\":3}");
+        // a continuation byte, a combining mark and an arrow cannot start a key
+        for invalid in [b"{\x80: 1}".as_ref(), "{\u{301}a: 1}".as_bytes(), "{←: 1}".as_bytes()] {
+            assert_eq!(JSONReader::new(invalid).check_lenient().unwrap_err().offset, 1);
+        }
+        assert_eq!(JSONReader::new("{a←: 1}".as_bytes()).check_lenient(), Err(ParseError{ kind: ErrorKind::UnexpectedToken(0xE2), offset: 2 }));
+    }
+
+    #[test]
+    fn test_is_number() {
+        for valid in ["0", "+1", "-.5", "5.", "0x1f", "-Infinity", "NaN", "1.5e+3", ".5E2"].iter() {
+            assert!(is_number(valid.as_bytes()), "{}", valid);
+        }
+        for invalid in ["", ".", "+", "01", "0x", "1e", "Inf", "0xg", "1.2.3"].iter() {
+            assert!(!is_number(invalid.as_bytes()), "{}", invalid);
+        }
+    }
+}
@@ -0,0 +1,30 @@
+pub mod encoding;
+pub mod decimal;
+pub mod duplicates;
+pub mod engine;
+#[cfg(any(test, feature = "fuzzing"))]
+#[doc(hidden)]
+pub mod harness;
+pub mod iter;
+pub mod lazy;
+pub mod lenient;
+pub mod limits;
+pub mod minify;
+pub mod mmap;
+pub mod ndjson;
+pub mod parser;
+pub mod patch;
+pub mod path;
+pub mod query;
+pub mod reader;
+pub mod result;
+pub mod shared;
+pub mod sourcemap;
+pub mod sidecar;
+pub mod stream;
+pub mod string;
+pub mod structural;
+pub mod suggest;
+pub mod tape;
+pub mod typed;
+pub mod validation;
@@ -0,0 +1,73 @@
+//! Caps on the size and shape of a document, for input from untrusted sources.
+//!
+//! The parser checks them as it goes, [Limits::default] unless given others, and fails
+//! with [crate::parser::ErrorKind::LimitExceeded] at the first token past a limit, so
+//! hostile input is turned away before it costs more than the limits allow.
+
+use std::fmt;
+
+/// Which of the [Limits] a document went past
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Limit {
+    Depth,
+    DocumentSize,
+    StringLength,
+    NumberLength,
+    ObjectMembers,
+    ArrayLength,
+}
+
+impl fmt::Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Limit::Depth => write!(f, "nesting depth"),
+            Limit::DocumentSize => write!(f, "document size"),
+            Limit::StringLength => write!(f, "string length"),
+            Limit::NumberLength => write!(f, "number length"),
+            Limit::ObjectMembers => write!(f, "object members"),
+            Limit::ArrayLength => write!(f, "array length"),
+        }
+    }
+}
+
+/// Sizes are in bytes, strings and keys are measured without their quotes
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Limits {
+    /// How many containers may be open at once
+    pub max_depth: usize,
+    pub max_document_size: usize,
+    pub max_string_length: usize,
+    pub max_number_length: usize,
+    pub max_object_members: usize,
+    pub max_array_length: usize,
+}
+
+impl Limits {
+    /// No limits at all, for trusted input that may be larger than [Limits::default] allows
+    pub fn unlimited() -> Limits {
+        Limits{
+            max_depth: usize::MAX,
+            max_document_size: usize::MAX,
+            max_string_length: usize::MAX,
+            max_number_length: usize::MAX,
+            max_object_members: usize::MAX,
+            max_array_length: usize::MAX,
+        }
+    }
+}
+
+impl Default for Limits {
+    /// What [crate::parser::Parser] and the validator enforce unless told otherwise. Lookups
+    /// and the file-backed tapes and maps read trusted data and apply no limits.
+    /// Generous for legitimate documents while keeping hostile ones cheap to reject.
+    fn default() -> Limits {
+        Limits{
+            max_depth: 128,
+            max_document_size: 256 * 1024 * 1024,
+            max_string_length: 16 * 1024 * 1024,
+            max_number_length: 1024,
+            max_object_members: 1_000_000,
+            max_array_length: 10_000_000,
+        }
+    }
+}
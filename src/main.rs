@@ -1,9 +1,4 @@
-mod minify;
-mod validation;
-mod engine;
-mod query;
-mod result;
-mod reader;
+use gsjf::{engine, minify};
 
 /// syntax:
 /// .path
@@ -22,7 +17,7 @@ fn main() {
 }
     "#.as_bytes();
 
-    let json2: &[u8] = r#"
+    let _json2: &[u8] = r#"
 {
   "name": "alex",
   "boy": true,
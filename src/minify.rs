@@ -1,28 +1,70 @@
-use crate::reader::{JSONReader, Reader};
-use std::borrow::Borrow;
+use crate::parser::{Event, ParseError, Parser, Token};
+use crate::reader::{JSONReader, JSONType, Reader};
 
+/// Re-emits the document's tokens without any insignificant whitespace.
+/// Returns None if the document is not valid JSON.
 pub fn minify(json: &[u8]) -> Option<Box<[u8]>> {
     let mut reader: JSONReader = JSONReader::new(json);
-    let mut new: Vec<u8> = vec!();
-    while let Some(token) = reader.next() {
-        match token {
-            t if t > b' ' => {
-                new.push(t);
-                if t == b'"' {
-                    while let Some(end_string_candidate) = reader.next() {
-                        new.push(end_string_candidate);
-                        match end_string_candidate {
-                            b'"' => if reader.last() != Some(b'\\') {
-                                break
-                            }
-                            _ => ()
-                        }
-                    }
-                }
-            }
+    emit(json, Parser::new(&mut reader), |out: &mut Vec<u8>, token: &Token| {
+        let bytes: &[u8] = &json[token.range.0..token.range.1];
+        match token.event {
+            Event::Key | Event::Value(JSONType::STRING) => {
+                out.push(b'"');
+                out.extend_from_slice(bytes);
+                out.push(b'"');
+            },
+            _ => out.extend_from_slice(bytes)
+        }
+        Ok(())
+    }).ok()
+}
+
+/// Writes the tokens of [parser] compactly, adding the commas and colons between them.
+/// [write] emits every key and scalar, brackets are copied as they are.
+pub(crate) fn emit<R, W>(json: &[u8], parser: Parser<R>, mut write: W) -> Result<Box<[u8]>, ParseError>
+    where R: Reader, W: FnMut(&mut Vec<u8>, &Token) -> Result<(), ParseError> {
+    let mut new: Vec<u8> = Vec::with_capacity(json.len());
+    let mut needs_comma: bool = false;
+    for token in parser {
+        let token: Token = token?;
+        match token.event {
+            Event::EndObject | Event::EndArray => {
+                new.extend_from_slice(&json[token.range.0..token.range.1]);
+                needs_comma = true;
+                continue
+            },
+            _ if needs_comma => new.push(b','),
             _ => {}
         }
-        if token > b' ' {}
+        match token.event {
+            Event::StartObject | Event::StartArray => new.extend_from_slice(&json[token.range.0..token.range.1]),
+            Event::Key => {
+                write(&mut new, &token)?;
+                new.push(b':');
+            },
+            _ => write(&mut new, &token)?
+        }
+        needs_comma = matches!(token.event, Event::Value(_));
+    }
+    Ok(new.into_boxed_slice())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::minify::minify;
+
+    #[test]
+    fn test_minify() {
+        let json: &[u8] = br#"
+{
+  "name": "a l e x",
+  "hobbies": [ "cooking" , "gui\"tar" ],
+  "nested": { "empty": {}, "list": [ [ ], 1 ] },
+  "age":  32
+}
+"#;
+        let expected: &[u8] = br#"{"name":"a l e x","hobbies":["cooking","gui\"tar"],"nested":{"empty":{},"list":[[],1]},"age":32}"#;
+        assert_eq!(minify(json).unwrap().as_ref(), expected);
+        assert_eq!(minify(b"[1, 2,]"), None);
     }
-    Some(new.into_boxed_slice())
-}
\ No newline at end of file
+}
@@ -0,0 +1,192 @@
+//! Memory-mapped input for large on-disk documents.
+//!
+//! The file is mapped read-only and private, so [JSONReader] runs directly over the
+//! page cache instead of a copy of the file in a Vec. The mapping lives exactly as long
+//! as the [MappedFile], which is what every borrowed reader and value is tied to.
+//!
+//! The contents must not change while mapped: truncating the file from another process
+//! makes reads past the new end fault. Platforms without mmap read the file instead.
+//!
+//! Mapped files are trusted local data, so [MappedFile::validate] and [MappedFile::check]
+//! apply no limits; [MappedFile::check_with_limits] brings them back.
+
+use std::convert::TryFrom;
+use std::fs::File;
+use std::io;
+use std::path::Path;
+use crate::engine;
+use crate::limits::Limits;
+use crate::parser::ParseError;
+use crate::query::Query;
+use crate::reader::{JSONReader, JSONValue};
+use crate::typed::ValueError;
+use crate::validation::Validator;
+
+pub struct MappedFile {
+    #[cfg(unix)]
+    map: Option<Mapping>,
+    #[cfg(not(unix))]
+    data: Vec<u8>,
+}
+
+#[cfg(unix)]
+struct Mapping {
+    ptr: std::ptr::NonNull<u8>,
+    len: usize,
+}
+
+// The mapping is read-only and owned, sharing it is no different from sharing a &[u8]
+unsafe impl Send for MappedFile {}
+unsafe impl Sync for MappedFile {}
+
+impl MappedFile {
+    /// Maps the whole file at [path]. Empty files are valid and map to no bytes.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<MappedFile> {
+        MappedFile::from_file(&File::open(path)?)
+    }
+
+    #[cfg(unix)]
+    pub fn from_file(file: &File) -> io::Result<MappedFile> {
+        use std::os::unix::io::AsRawFd;
+
+        let len: usize = match usize::try_from(file.metadata()?.len()) {
+            Ok(len) => len,
+            Err(_) => return Err(io::Error::new(io::ErrorKind::InvalidInput, "file too large to map"))
+        };
+        if len == 0 {
+            return Ok(MappedFile{ map: None }) // mmap rejects zero length mappings
+        }
+        let ptr = unsafe {
+            libc::mmap(std::ptr::null_mut(), len, libc::PROT_READ, libc::MAP_PRIVATE, file.as_raw_fd(), 0)
+        };
+        if ptr == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error())
+        }
+        match std::ptr::NonNull::new(ptr as *mut u8) {
+            Some(ptr) => Ok(MappedFile{ map: Some(Mapping{ ptr, len }) }),
+            None => Err(io::Error::other("mmap returned a null mapping"))
+        }
+    }
+
+    #[cfg(not(unix))]
+    pub fn from_file(mut file: &File) -> io::Result<MappedFile> {
+        use std::io::Read;
+
+        let mut data: Vec<u8> = vec!();
+        file.read_to_end(&mut data)?;
+        Ok(MappedFile{ data })
+    }
+
+    /// The contents of the file
+    #[cfg(unix)]
+    pub fn as_bytes(&self) -> &[u8] {
+        match &self.map {
+            None => &[],
+            Some(map) => unsafe { std::slice::from_raw_parts(map.ptr.as_ptr(), map.len) }
+        }
+    }
+
+    #[cfg(not(unix))]
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn len(&self) -> usize {
+        self.as_bytes().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// A reader positioned at the start of the file
+    pub fn reader(&self) -> JSONReader<'_> {
+        JSONReader::new(self.as_bytes())
+    }
+
+    /// Resolves [query] against the file, see [engine::select]
+    pub fn select(&self, query: &Query) -> Vec<JSONValue> {
+        engine::select(self.as_bytes(), query)
+    }
+
+    /// The bytes of a value selected from this file, strings exclude their quotes.
+    /// Fails if the value was read from some other document.
+    pub fn slice(&self, value: &JSONValue) -> Result<&[u8], ValueError> {
+        value.bytes(self.as_bytes())
+    }
+
+    pub fn validate(&self) -> bool {
+        self.check().is_ok()
+    }
+
+    /// Validates the file, reporting where it first goes wrong. No limits apply.
+    pub fn check(&self) -> Result<(), ParseError> {
+        self.check_with_limits(Limits::unlimited())
+    }
+
+    /// Validates the file, failing once it goes past [limits]
+    pub fn check_with_limits(&self, limits: Limits) -> Result<(), ParseError> {
+        self.reader().check_with_limits(limits)
+    }
+}
+
+impl AsRef<[u8]> for MappedFile {
+    fn as_ref(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+#[cfg(unix)]
+impl Drop for MappedFile {
+    fn drop(&mut self) {
+        if let Some(map) = &self.map {
+            unsafe {
+                libc::munmap(map.ptr.as_ptr() as *mut libc::c_void, map.len);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use crate::limits::{Limit, Limits};
+    use crate::mmap::MappedFile;
+    use crate::parser::ErrorKind;
+    use crate::query::Query;
+    use crate::reader::{JSONType, JSONValue};
+    use crate::typed::ValueError;
+
+    fn write(name: &str, contents: &[u8]) -> PathBuf {
+        let path: PathBuf = std::env::temp_dir().join(format!("gsjf-{}-{}.json", std::process::id(), name));
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_select() {
+        let path: PathBuf = write("select", br#"{"name":"alex","hobbies":["cooking","guitar"]}"#);
+        let file: MappedFile = MappedFile::open(&path).unwrap();
+        assert!(file.validate());
+        let selected: Vec<JSONValue> = file.select(&Query::from("hobbies.[1]"));
+        assert_eq!(selected.len(), 1);
+        assert_eq!(selected[0].json_type(), JSONType::STRING);
+        assert_eq!(file.slice(&selected[0]), Ok(b"guitar".as_ref()));
+        assert_eq!(file.slice(&JSONValue::new(JSONType::STRING, (40, 60))), Err(ValueError::OutOfBounds));
+        let limits: Limits = Limits{ max_document_size: 16, ..Limits::default() };
+        assert_eq!(file.check_with_limits(limits).unwrap_err().kind, ErrorKind::LimitExceeded(Limit::DocumentSize));
+        drop(file);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_empty_and_missing() {
+        let path: PathBuf = write("empty", b"");
+        let file: MappedFile = MappedFile::open(&path).unwrap();
+        assert!(file.is_empty());
+        assert!(!file.validate());
+        assert!(file.select(&Query::from("name")).is_empty());
+        std::fs::remove_file(&path).unwrap();
+        assert!(MappedFile::open(&path).is_err());
+    }
+}
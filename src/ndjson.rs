@@ -0,0 +1,175 @@
+//! Newline-delimited JSON: one document per line.
+//!
+//! Blank lines are ignored and a trailing \r is stripped, so \r\n files work too.
+//! Line numbers start at 1 and count every line, blank or not, to match an editor.
+//! Each operation takes an [OnError] deciding whether a bad line aborts the whole run
+//! or is left out of the output.
+
+use std::fmt;
+use crate::engine;
+use crate::minify;
+use crate::parser::ParseError;
+use crate::query::Query;
+use crate::reader::{JSONReader, JSONValue};
+use crate::validation::Validator;
+
+/// One line holding a document
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Record<'a> {
+    pub line: usize,
+    /// Where the line starts in the whole input
+    pub offset: usize,
+    pub data: &'a [u8],
+}
+
+impl Record<'_> {
+    pub fn check(&self) -> Result<(), LineError> {
+        JSONReader::new(self.data).check().map_err(|error| LineError{ line: self.line, error })
+    }
+}
+
+/// Yields every non-blank line of the input
+pub struct Records<'a> {
+    data: &'a [u8],
+    offset: usize,
+    line: usize,
+}
+
+pub fn records(data: &[u8]) -> Records<'_> {
+    Records{ data, offset: 0, line: 0 }
+}
+
+impl<'a> Iterator for Records<'a> {
+    type Item = Record<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.offset < self.data.len() {
+            let rest: &'a [u8] = &self.data[self.offset..];
+            let len: usize = rest.iter().position(|byte| *byte == b'\n').unwrap_or(rest.len());
+            let record: Record<'a> = Record{
+                line: self.line + 1,
+                offset: self.offset,
+                data: rest[..len].strip_suffix(b"\r").unwrap_or(&rest[..len]),
+            };
+            self.line += 1;
+            self.offset += len + 1;
+            if !record.data.iter().all(|byte| matches!(byte, b' ' | b'\t' | b'\r')) {
+                return Some(record)
+            }
+        }
+        None
+    }
+}
+
+/// What to do with a line that is not valid JSON
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum OnError {
+    /// Stop and return the line's error
+    Abort,
+    /// Leave the line out and carry on
+    Skip,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct LineError {
+    pub line: usize,
+    /// The offset of the error is relative to the start of the line
+    pub error: ParseError,
+}
+
+impl fmt::Display for LineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.error)
+    }
+}
+
+impl std::error::Error for LineError {}
+
+/// The error of every invalid line
+pub fn check(data: &[u8]) -> Vec<LineError> {
+    records(data).filter_map(|record| record.check().err()).collect()
+}
+
+/// The valid records of [data], or the first error when [on_error] aborts
+fn valid(data: &[u8], on_error: OnError) -> Result<Vec<Record<'_>>, LineError> {
+    let mut valid: Vec<Record> = vec!();
+    for record in records(data) {
+        match (record.check(), on_error) {
+            (Ok(()), _) => valid.push(record),
+            (Err(error), OnError::Abort) => return Err(error),
+            (Err(_), OnError::Skip) => {}
+        }
+    }
+    Ok(valid)
+}
+
+/// Runs [query] against every line. Ranges are offsets into the whole of [data].
+pub fn select(data: &[u8], query: &Query, on_error: OnError) -> Result<Vec<(usize, Vec<JSONValue>)>, LineError> {
+    Ok(valid(data, on_error)?.into_iter()
+        .map(|record| {
+            let values: Vec<JSONValue> = engine::select(record.data, query).into_iter()
+                .map(|value| JSONValue::new(value.json_type, (value.range.0 + record.offset, value.range.1 + record.offset)))
+                .collect();
+            (record.line, values)
+        })
+        .collect())
+}
+
+/// Minifies every line, keeping one document per line and dropping blank lines
+pub fn minify(data: &[u8], on_error: OnError) -> Result<Vec<u8>, LineError> {
+    let mut minified: Vec<u8> = Vec::with_capacity(data.len());
+    for record in valid(data, on_error)? {
+        if let Some(line) = minify::minify(record.data) {
+            minified.extend_from_slice(&line);
+            minified.push(b'\n');
+        }
+    }
+    Ok(minified)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ndjson::{check, minify, records, select, OnError, Record};
+    use crate::parser::ErrorKind;
+    use crate::query::Query;
+    use crate::reader::JSONValue;
+
+    const NDJSON: &[u8] = b"{\"id\": 1, \"tags\": [\"a\"]}\r\n\n{\"id\": 2, \"tags\": []}\n{\"id\": 3,}\n  \n[4]";
+
+    #[test]
+    fn test_records() {
+        let lines: Vec<(usize, &[u8])> = records(NDJSON).map(|record: Record| (record.line, record.data)).collect();
+        assert_eq!(lines, vec!(
+            (1, br#"{"id": 1, "tags": ["a"]}"#.as_ref()),
+            (3, br#"{"id": 2, "tags": []}"#),
+            (4, br#"{"id": 3,}"#),
+            (6, b"[4]"),
+        ));
+    }
+
+    #[test]
+    fn test_check() {
+        let errors = check(NDJSON);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].line, 4);
+        assert_eq!(errors[0].error.kind, ErrorKind::UnexpectedToken(b'}'));
+        assert_eq!(errors[0].to_string(), "line 4: unexpected '}' at offset 9");
+    }
+
+    #[test]
+    fn test_select() {
+        assert_eq!(select(NDJSON, &Query::from("id"), OnError::Abort).unwrap_err().line, 4);
+        let ids: Vec<(usize, Vec<&[u8]>)> = select(NDJSON, &Query::from("id"), OnError::Skip).unwrap().into_iter()
+            .map(|(line, values): (usize, Vec<JSONValue>)| {
+                (line, values.iter().map(|value| &NDJSON[value.range().0..value.range().1]).collect())
+            })
+            .collect();
+        assert_eq!(ids, vec!((1, vec!(b"1".as_ref())), (3, vec!(b"2".as_ref())), (6, vec!())));
+    }
+
+    #[test]
+    fn test_minify() {
+        assert!(minify(NDJSON, OnError::Abort).is_err());
+        assert_eq!(minify(NDJSON, OnError::Skip).unwrap(), b"{\"id\":1,\"tags\":[\"a\"]}\n{\"id\":2,\"tags\":[]}\n[4]\n".to_vec());
+    }
+}
@@ -0,0 +1,602 @@
+//! A pull parser emitting the structure of a document as a stream of tokens.
+//!
+//! Only the stack of open containers is kept, so arbitrarily large documents are
+//! processed in memory proportional to their nesting depth. Every token carries the
+//! byte range it was read from, strings and keys exclude their quotes.
+
+use std::collections::HashMap;
+use std::fmt;
+use crate::duplicates::DuplicateKeys;
+use crate::encoding::{self, Encoding};
+use crate::lenient;
+use crate::limits::{Limit, Limits};
+use crate::reader::{JSONRange, JSONType, Reader};
+use crate::string;
+use crate::validation::Validator;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Event {
+    StartObject,
+    EndObject,
+    StartArray,
+    EndArray,
+    Key,
+    /// A scalar: string, number, boolean or null
+    Value(JSONType),
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Token {
+    pub event: Event,
+    pub range: JSONRange,
+    /// How many containers enclose the token, the root value is at depth 0
+    pub depth: usize,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ErrorKind {
+    /// A byte that is not allowed where it appeared
+    UnexpectedToken(u8),
+    /// The document ended while a value was still expected
+    UnexpectedEnd,
+    InvalidString,
+    InvalidNumber,
+    /// Something starting like true, false or null that isn't
+    InvalidLiteral,
+    /// Anything but whitespace after the document
+    TrailingCharacters,
+    /// The input is UTF-16 or UTF-32, see [encoding::to_utf8]
+    UnsupportedEncoding(Encoding),
+    /// The document goes past one of the parser's [Limits]
+    LimitExceeded(Limit),
+    /// A key repeats within its object, the offset is where it first appeared
+    DuplicateKey(usize),
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct ParseError {
+    pub kind: ErrorKind,
+    /// Where the offending token starts
+    pub offset: usize,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            ErrorKind::UnexpectedToken(token) => write!(f, "unexpected '{}'", token.escape_ascii())?,
+            ErrorKind::UnexpectedEnd => write!(f, "unexpected end of input")?,
+            ErrorKind::InvalidString => write!(f, "invalid string")?,
+            ErrorKind::InvalidNumber => write!(f, "invalid number")?,
+            ErrorKind::InvalidLiteral => write!(f, "invalid literal")?,
+            ErrorKind::TrailingCharacters => write!(f, "trailing characters after the document")?,
+            ErrorKind::UnsupportedEncoding(encoding) => write!(f, "input is {}, transcode it to UTF-8 first", encoding)?,
+            ErrorKind::LimitExceeded(limit) => write!(f, "{} limit exceeded", limit)?,
+            ErrorKind::DuplicateKey(first) => write!(f, "duplicate key (first at offset {})", first)?,
+        };
+        write!(f, " at offset {}", self.offset)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Container {
+    Object,
+    Array,
+}
+
+/// What the parser expects to read next
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum State {
+    /// The root value
+    Document,
+    /// Just past a {, a key or }
+    ObjectStart,
+    /// Just past a comma in an object
+    Key,
+    /// Just past a key
+    Colon,
+    /// Just past a [, a value or ]
+    ArrayStart,
+    /// Just past a colon or a comma in an array
+    Value,
+    /// Just past a value inside a container, a comma or its close
+    AfterValue,
+    /// The root value is complete, only whitespace may follow unless documents are concatenated
+    End,
+}
+
+/// Parses one document from the reader's current position. A document is any value,
+/// scalars included, as RFC 8259 allows.
+pub struct Parser<'r, R: Reader + ?Sized> {
+    reader: &'r mut R,
+    stack: Vec<Container>,
+    /// How many members or elements each open container has so far
+    counts: Vec<usize>,
+    state: State,
+    failed: bool,
+    concatenated: bool,
+    lenient: bool,
+    limits: Limits,
+    duplicates: DuplicateKeys,
+    /// The decoded keys of each open container with where they start, only kept
+    /// when duplicates are an error
+    keys: Vec<HashMap<Vec<u8>, usize>>,
+    /// Where parsing started, document sizes are measured from here
+    start: usize,
+}
+
+impl<'r, R: Reader + ?Sized> Parser<'r, R> {
+    pub fn new(reader: &'r mut R) -> Parser<'r, R> {
+        let start: usize = reader.offset();
+        Parser{
+            reader,
+            stack: vec!(),
+            counts: vec!(),
+            state: State::Document,
+            failed: false,
+            concatenated: false,
+            lenient: false,
+            limits: Limits::default(),
+            duplicates: DuplicateKeys::FirstWins,
+            keys: vec!(),
+            start,
+        }
+    }
+
+    /// Parses any number of documents following each other, like `{..}{..}[..]` or `1 2`.
+    /// Every document starts again at depth 0, and no documents at all is fine too.
+    pub fn concatenated(reader: &'r mut R) -> Parser<'r, R> {
+        Parser{ concatenated: true, ..Parser::new(reader) }
+    }
+
+    /// Also accepts JSONC and JSON5: comments, trailing commas, single quoted strings,
+    /// unquoted keys and JSON5 numbers. Tokens still exclude quotes, see [lenient] for
+    /// turning them back into strict JSON.
+    pub fn lenient(reader: &'r mut R) -> Parser<'r, R> {
+        Parser{ lenient: true, ..Parser::new(reader) }
+    }
+
+    /// Fails with [ErrorKind::LimitExceeded] once the input goes past [limits] rather than
+    /// [Limits::default], pass [Limits::unlimited] to turn them off
+    pub fn with_limits(mut self, limits: Limits) -> Parser<'r, R> {
+        self.limits = limits;
+        self
+    }
+
+    /// Fails with [ErrorKind::DuplicateKey] on a repeated key when [duplicates] is
+    /// [DuplicateKeys::Error], the other policies only matter to lookups
+    pub fn with_duplicate_keys(mut self, duplicates: DuplicateKeys) -> Parser<'r, R> {
+        self.duplicates = duplicates;
+        self
+    }
+
+    /// How many containers are currently open
+    pub fn depth(&self) -> usize {
+        self.stack.len()
+    }
+
+    /// The reader being parsed, e.g. to select the range of a token
+    pub(crate) fn reader(&mut self) -> &mut R {
+        self.reader
+    }
+
+    fn error(&self, kind: ErrorKind, offset: usize) -> Result<Option<Token>, ParseError> {
+        Err(ParseError{ kind, offset })
+    }
+
+    fn token(&self, event: Event, range: JSONRange, depth: usize) -> Result<Option<Token>, ParseError> {
+        self.check_size(range.1, range.0)?;
+        Ok(Some(Token{ event, range, depth }))
+    }
+
+    /// Fails if the document reaches past its size limit by [end], blaming [offset]
+    fn check_size(&self, end: usize, offset: usize) -> Result<(), ParseError> {
+        match end.saturating_sub(self.start) > self.limits.max_document_size {
+            true => Err(ParseError{ kind: ErrorKind::LimitExceeded(Limit::DocumentSize), offset }),
+            false => Ok(())
+        }
+    }
+
+    /// Counts another member or element of the innermost container against its limit
+    fn count(&mut self, position: usize) -> Result<(), ParseError> {
+        let (limit, max): (Limit, usize) = match self.stack.last() {
+            None => return Ok(()),
+            Some(Container::Object) => (Limit::ObjectMembers, self.limits.max_object_members),
+            Some(Container::Array) => (Limit::ArrayLength, self.limits.max_array_length),
+        };
+        if let Some(count) = self.counts.last_mut() {
+            *count += 1;
+            if *count > max {
+                return Err(ParseError{ kind: ErrorKind::LimitExceeded(limit), offset: position })
+            }
+        }
+        Ok(())
+    }
+
+    /// Opens a container, unless that nests too deep
+    fn open(&mut self, container: Container, position: usize) -> Result<(), ParseError> {
+        if self.stack.len() >= self.limits.max_depth {
+            return Err(ParseError{ kind: ErrorKind::LimitExceeded(Limit::Depth), offset: position })
+        }
+        self.stack.push(container);
+        self.counts.push(0);
+        self.keys.push(HashMap::new());
+        Ok(())
+    }
+
+    /// Emits the key at [range], rejecting it if it repeats and that is an error
+    fn key(&mut self, range: JSONRange) -> Result<Option<Token>, ParseError> {
+        if self.duplicates == DuplicateKeys::Error {
+            let key: Vec<u8> = match self.reader.select(range) {
+                None => vec!(),
+                Some(raw) => match string::decode(raw) {
+                    Ok(decoded) => decoded.into_owned().into_bytes(),
+                    Err(_) => raw.to_vec()
+                }
+            };
+            if let Some(keys) = self.keys.last_mut() {
+                if let Some(first) = keys.insert(key, range.0) {
+                    return self.error(ErrorKind::DuplicateKey(first), range.0)
+                }
+            }
+        }
+        self.state = State::Colon;
+        self.token(Event::Key, range, self.stack.len())
+    }
+
+    /// Moves on once a complete value has been read
+    fn finish_value(&mut self) {
+        self.state = match self.stack.is_empty() {
+            true => State::End,
+            false => State::AfterValue
+        };
+    }
+
+    fn step(&mut self) -> Result<Option<Token>, ParseError> {
+        let position: usize = self.reader.position();
+        self.reader.release(position); // earlier tokens have already been handed out
+        loop {
+            self.skip_insignificant()?;
+            let token: u8 = match self.reader.next() {
+                Some(token) => token,
+                None => return match self.state {
+                    State::End => Ok(None),
+                    State::Document if self.concatenated => Ok(None),
+                    _ => {
+                        let end: usize = match self.reader.last() {
+                            None => 0,
+                            Some(_) => self.reader.position() + 1
+                        };
+                        self.error(ErrorKind::UnexpectedEnd, end)
+                    }
+                }
+            };
+            let position: usize = self.reader.position();
+            self.check_size(position + 1, position)?;
+            match (self.state, token) {
+                (State::Document, 0xEF) if position == 0 => self.byte_order_mark()?,
+                (State::Document, 0x00) | (State::Document, 0xFE) | (State::Document, 0xFF) if position == 0 => {
+                    return self.unsupported_encoding(token)
+                },
+                (State::End, _) if self.concatenated => return self.value(token, position),
+                (State::End, _) => return self.error(ErrorKind::TrailingCharacters, position),
+                (State::ObjectStart, b'}') | (State::AfterValue, b'}') if self.stack.last() == Some(&Container::Object) => {
+                    return self.close(Event::EndObject, position)
+                },
+                (State::ArrayStart, b']') | (State::AfterValue, b']') if self.stack.last() == Some(&Container::Array) => {
+                    return self.close(Event::EndArray, position)
+                },
+                // trailing commas
+                (State::Key, b'}') if self.lenient => return self.close(Event::EndObject, position),
+                (State::Value, b']') if self.lenient && self.stack.last() == Some(&Container::Array) => {
+                    return self.close(Event::EndArray, position)
+                },
+                (State::ObjectStart, b'"') | (State::Key, b'"') => {
+                    self.count(position)?;
+                    let range: JSONRange = self.string(position, token)?;
+                    return self.key(range)
+                },
+                (State::ObjectStart, _) | (State::Key, _) if self.lenient => {
+                    self.count(position)?;
+                    let range: Option<JSONRange> = match token {
+                        b'\'' => Some(self.string(position, token)?),
+                        _ => self.identifier(token, position)
+                    };
+                    let range: JSONRange = match range {
+                        Some(range) => range,
+                        None => return self.error(ErrorKind::UnexpectedToken(token), position)
+                    };
+                    return self.key(range)
+                },
+                (State::Colon, b':') => self.state = State::Value,
+                (State::AfterValue, b',') => {
+                    self.state = match self.stack.last() {
+                        Some(Container::Object) => State::Key,
+                        _ => State::Value
+                    }
+                },
+                (State::Document, _) | (State::ArrayStart, _) | (State::Value, _) => return self.value(token, position),
+                _ => return self.error(ErrorKind::UnexpectedToken(token), position)
+            }
+        }
+    }
+
+    fn close(&mut self, event: Event, position: usize) -> Result<Option<Token>, ParseError> {
+        self.stack.pop();
+        self.counts.pop();
+        self.keys.pop();
+        self.finish_value();
+        self.token(event, (position, position + 1), self.stack.len())
+    }
+
+    /// Skips the rest of a UTF-8 byte order mark
+    fn byte_order_mark(&mut self) -> Result<(), ParseError> {
+        match (self.reader.next(), self.reader.next()) {
+            (Some(0xBB), Some(0xBF)) => Ok(()),
+            _ => Err(ParseError{ kind: ErrorKind::UnexpectedToken(0xEF), offset: 0 })
+        }
+    }
+
+    /// Reports wider encodings given away by their first bytes, assuming [first] has been read
+    fn unsupported_encoding(&mut self, first: u8) -> Result<Option<Token>, ParseError> {
+        let mut start: Vec<u8> = vec!(first);
+        start.extend((0..3).map_while(|_| self.reader.next()));
+        match encoding::detect(&start) {
+            (Encoding::UTF8, _) => self.error(ErrorKind::UnexpectedToken(first), 0),
+            (encoding, _) => self.error(ErrorKind::UnsupportedEncoding(encoding), 0)
+        }
+    }
+
+    /// Skips whitespace, and comments when lenient
+    fn skip_insignificant(&mut self) -> Result<(), ParseError> {
+        loop {
+            self.reader.skip_past_whitespace();
+            if !self.lenient || self.reader.peek() != Some(b'/') {
+                return Ok(())
+            }
+            let start: usize = self.reader.offset();
+            if !lenient::skip_comment(self.reader) {
+                let kind: ErrorKind = match self.reader.peek() {
+                    None => ErrorKind::UnexpectedEnd,
+                    Some(_) => ErrorKind::UnexpectedToken(b'/')
+                };
+                return Err(ParseError{ kind, offset: start })
+            }
+        }
+    }
+
+    /// Assumes the opening [quote] at [position] has been read, returns the contents' range
+    fn string(&mut self, position: usize, quote: u8) -> Result<JSONRange, ParseError> {
+        let valid: bool = match self.lenient {
+            true => lenient::validate_string(self.reader, quote),
+            false => self.reader.validate_string()
+        };
+        if !valid {
+            return Err(ParseError{ kind: ErrorKind::InvalidString, offset: position })
+        }
+        let range: JSONRange = (position + 1, self.reader.position());
+        match range.1 - range.0 > self.limits.max_string_length {
+            true => Err(ParseError{ kind: ErrorKind::LimitExceeded(Limit::StringLength), offset: position }),
+            false => Ok(range)
+        }
+    }
+
+    /// Assumes [first], the first byte of an unquoted key at [position], has been read.
+    /// None if it does not start an identifier.
+    fn identifier(&mut self, first: u8, position: usize) -> Option<JSONRange> {
+        if !lenient::read_char(self.reader, first).is_some_and(lenient::is_identifier_start) {
+            return None
+        }
+        loop {
+            let before: usize = self.reader.offset();
+            match self.reader.next() {
+                None => break,
+                Some(byte) if lenient::read_char(self.reader, byte).is_some_and(lenient::is_identifier_part) => {},
+                Some(_) => {
+                    self.reader.seek(before); // the colon or whatever follows the key
+                    break
+                }
+            }
+        }
+        Some((position, self.reader.offset()))
+    }
+
+    /// Assumes [token], the first byte of a value at [position], has been read
+    fn value(&mut self, token: u8, position: usize) -> Result<Option<Token>, ParseError> {
+        let depth: usize = self.stack.len();
+        if depth == 0 && self.concatenated {
+            self.start = position; // each document of a stream is measured on its own
+        }
+        if self.stack.last() == Some(&Container::Array) {
+            self.count(position)?;
+        }
+        let (event, valid): (Event, bool) = match token {
+            b'{' => {
+                self.open(Container::Object, position)?;
+                self.state = State::ObjectStart;
+                return self.token(Event::StartObject, (position, position + 1), depth)
+            },
+            b'[' => {
+                self.open(Container::Array, position)?;
+                self.state = State::ArrayStart;
+                return self.token(Event::StartArray, (position, position + 1), depth)
+            },
+            b'"' | b'\'' if token == b'"' || self.lenient => {
+                let range: JSONRange = self.string(position, token)?;
+                self.finish_value();
+                return self.token(Event::Value(JSONType::STRING), range, depth)
+            },
+            b'-' | b'+' | b'.' | b'0'..=b'9' | b'I' | b'N' if self.lenient => {
+                (Event::Value(JSONType::NUMBER), lenient::validate_number(self.reader))
+            },
+            b'-' | b'0'..=b'9' => (Event::Value(JSONType::NUMBER), self.reader.validate_number()),
+            b't' | b'f' => (Event::Value(JSONType::BOOLEAN), self.reader.validate_boolean()),
+            b'n' => (Event::Value(JSONType::NULL), self.reader.validate_null()),
+            _ => return self.error(ErrorKind::UnexpectedToken(token), position)
+        };
+        if !valid {
+            let kind: ErrorKind = match event {
+                Event::Value(JSONType::NUMBER) => ErrorKind::InvalidNumber,
+                _ => ErrorKind::InvalidLiteral
+            };
+            return self.error(kind, position)
+        }
+        let end: usize = self.reader.position() + 1;
+        if event == Event::Value(JSONType::NUMBER) && end - position > self.limits.max_number_length {
+            return self.error(ErrorKind::LimitExceeded(Limit::NumberLength), position)
+        }
+        self.finish_value();
+        self.token(event, (position, end), depth)
+    }
+}
+
+impl<R: Reader + ?Sized> Iterator for Parser<'_, R> {
+    type Item = Result<Token, ParseError>;
+
+    /// Yields every token of the document, then None. An error is yielded once and ends iteration.
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None
+        }
+        match self.step() {
+            Ok(token) => token.map(Ok),
+            Err(error) => {
+                self.failed = true;
+                Some(Err(error))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::encoding::Encoding;
+    use crate::limits::{Limit, Limits};
+    use crate::parser::{ErrorKind, Event, ParseError, Parser, Token};
+    use crate::reader::{JSONReader, JSONType};
+    use crate::validation::Validator;
+
+    fn tokens(json: &[u8]) -> Result<Vec<Token>, ParseError> {
+        let mut reader: JSONReader = JSONReader::new(json);
+        Parser::new(&mut reader).collect()
+    }
+
+    fn error(json: &[u8]) -> ParseError {
+        tokens(json).unwrap_err()
+    }
+
+    #[test]
+    fn test_events() {
+        let json: &[u8] = br#" {"a": [1, "x\"y"], "b": {}, "c": null} "#;
+        let events: Vec<(Event, &[u8], usize)> = tokens(json).unwrap().into_iter()
+            .map(|token| (token.event, &json[token.range.0..token.range.1], token.depth))
+            .collect();
+        assert_eq!(events, vec!(
+            (Event::StartObject, b"{".as_ref(), 0),
+            (Event::Key, b"a", 1),
+            (Event::StartArray, b"[", 1),
+            (Event::Value(JSONType::NUMBER), b"1", 2),
+            (Event::Value(JSONType::STRING), br#"x\"y"#, 2),
+            (Event::EndArray, b"]", 1),
+            (Event::Key, b"b", 1),
+            (Event::StartObject, b"{", 1),
+            (Event::EndObject, b"}", 1),
+            (Event::Key, b"c", 1),
+            (Event::Value(JSONType::NULL), b"null", 1),
+            (Event::EndObject, b"}", 0),
+        ));
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(error(b""), ParseError{ kind: ErrorKind::UnexpectedEnd, offset: 0 });
+        assert_eq!(error(b"[1, 2"), ParseError{ kind: ErrorKind::UnexpectedEnd, offset: 5 });
+        assert_eq!(error(b"[1,]"), ParseError{ kind: ErrorKind::UnexpectedToken(b']'), offset: 3 });
+        assert_eq!(error(br#"{"a" 1}"#), ParseError{ kind: ErrorKind::UnexpectedToken(b'1'), offset: 5 });
+        assert_eq!(error(br#"{"a": 01}"#), ParseError{ kind: ErrorKind::InvalidNumber, offset: 6 });
+        assert_eq!(error(br#"[nil]"#), ParseError{ kind: ErrorKind::InvalidLiteral, offset: 1 });
+        assert_eq!(error(br#"["a\q"]"#), ParseError{ kind: ErrorKind::InvalidString, offset: 1 });
+        assert_eq!(error(b"[1] [2]"), ParseError{ kind: ErrorKind::TrailingCharacters, offset: 4 });
+        assert_eq!(error(b"[1}"), ParseError{ kind: ErrorKind::UnexpectedToken(b'}'), offset: 2 });
+        assert_eq!(error(b"42 43"), ParseError{ kind: ErrorKind::TrailingCharacters, offset: 3 });
+        assert_eq!(error(b"}"), ParseError{ kind: ErrorKind::UnexpectedToken(b'}'), offset: 0 });
+        assert_eq!(error(b"\xFF\xFE[\x00]\x00"), ParseError{ kind: ErrorKind::UnsupportedEncoding(Encoding::UTF16LE), offset: 0 });
+        assert_eq!(error(b"\x00\x00\x00["), ParseError{ kind: ErrorKind::UnsupportedEncoding(Encoding::UTF32BE), offset: 0 });
+        assert_eq!(error(b"\xEF\xBB["), ParseError{ kind: ErrorKind::UnexpectedToken(0xEF), offset: 0 });
+        assert_eq!(tokens(b"\xEF\xBB\xBF [1]").unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_scalar_documents() {
+        for (json, event) in [(b" 42 ".as_ref(), Event::Value(JSONType::NUMBER)), (br#""x""#, Event::Value(JSONType::STRING)),
+                              (b"null", Event::Value(JSONType::NULL)), (b"false", Event::Value(JSONType::BOOLEAN))] {
+            let events: Vec<(Event, usize)> = tokens(json).unwrap().into_iter().map(|token| (token.event, token.depth)).collect();
+            assert_eq!(events, vec!((event, 0)));
+        }
+        assert_eq!(error(b"-"), ParseError{ kind: ErrorKind::InvalidNumber, offset: 0 });
+    }
+
+    #[test]
+    fn test_concatenated() {
+        let json: &[u8] = br#"{"a":1}{"b":[]}[2] "x"3 null"#;
+        let mut reader: JSONReader = JSONReader::new(json);
+        let roots: Vec<&[u8]> = Parser::concatenated(&mut reader)
+            .map(|token| token.unwrap())
+            .filter(|token| token.depth == 0 && token.event != Event::EndObject && token.event != Event::EndArray)
+            .map(|token| &json[token.range.0..token.range.1])
+            .collect();
+        assert_eq!(roots, vec!(b"{".as_ref(), b"{", b"[", b"x", b"3", b"null"));
+        let mut reader: JSONReader = JSONReader::new(b"  ");
+        assert_eq!(Parser::concatenated(&mut reader).count(), 0);
+        let mut reader: JSONReader = JSONReader::new(b"{}[");
+        assert!(Parser::concatenated(&mut reader).any(|token| token.is_err()));
+    }
+
+    #[test]
+    fn test_limits() {
+        let limits: Limits = Limits{
+            max_depth: 2,
+            max_document_size: 40,
+            max_string_length: 3,
+            max_number_length: 4,
+            max_object_members: 2,
+            max_array_length: 3,
+        };
+        let check = |json: &[u8]| -> Result<usize, ParseError> {
+            let mut reader: JSONReader = JSONReader::new(json);
+            Parser::new(&mut reader).with_limits(limits).try_fold(0, |count, token| token.map(|_| count + 1))
+        };
+        let exceeded = |limit: Limit, offset: usize| Err(ParseError{ kind: ErrorKind::LimitExceeded(limit), offset });
+        assert_eq!(check(br#"{"abc": [1234, "x", null], "b": []}"#), Ok(11));
+        assert_eq!(check(b"[[[]]]"), exceeded(Limit::Depth, 2));
+        assert_eq!(check(br#"["abcd"]"#), exceeded(Limit::StringLength, 1));
+        assert_eq!(check(br#"{"abcd": 1}"#), exceeded(Limit::StringLength, 1));
+        assert_eq!(check(b"[12345]"), exceeded(Limit::NumberLength, 1));
+        assert_eq!(check(br#"{"a": 1, "b": 2, "c": 3}"#), exceeded(Limit::ObjectMembers, 17));
+        assert_eq!(check(b"[1, 2, 3, 4]"), exceeded(Limit::ArrayLength, 10));
+        assert_eq!(check(br#"[["abc", "abc", "abc"], ["abc", "abc", "abc"]]"#), exceeded(Limit::DocumentSize, 40));
+        // documents of a stream are measured one at a time
+        let mut reader: JSONReader = JSONReader::new(br#"["abc", "abc", "abc"] ["abc", "abc", "abc"]"#);
+        assert!(Parser::concatenated(&mut reader).with_limits(limits).all(|token| token.is_ok()));
+    }
+
+    #[test]
+    fn test_default_limits() {
+        let deep: Vec<u8> = [vec!(b'['; 200), vec!(b']'; 200)].concat();
+        let mut reader: JSONReader = JSONReader::new(&deep);
+        assert_eq!(reader.check(), Err(ParseError{ kind: ErrorKind::LimitExceeded(Limit::Depth), offset: 128 }));
+        let mut reader: JSONReader = JSONReader::new(&deep);
+        assert!(Parser::new(&mut reader).with_limits(Limits::unlimited()).all(|token| token.is_ok()));
+    }
+
+    #[test]
+    fn test_error_ends_iteration() {
+        let mut reader: JSONReader = JSONReader::new(b"[1, x, 2]");
+        let mut parser = Parser::new(&mut reader);
+        assert!(parser.next().unwrap().is_ok());
+        assert!(parser.next().unwrap().is_ok());
+        assert!(parser.next().unwrap().is_err());
+        assert!(parser.next().is_none());
+    }
+}
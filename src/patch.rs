@@ -0,0 +1,109 @@
+//! Edits a document by splicing its bytes: only the replaced value changes, every other
+//! byte is kept as written. Numbers are copied as their exact text and never pass
+//! through f64, so editing one field leaves a `12345678901234567890.000001` elsewhere
+//! untouched, and setting one writes exactly the digits given.
+
+use std::fmt;
+use crate::decimal::Decimal;
+use crate::engine;
+use crate::parser::{ErrorKind, ParseError};
+use crate::query::Query;
+use crate::reader::{JSONRange, JSONReader, JSONValue, Reader};
+use crate::validation::Validator;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum PatchError {
+    /// The query's path leads nowhere in the document
+    NotFound,
+    /// The document being edited is not valid JSON
+    Document(ParseError),
+    /// The replacement is not exactly one valid JSON value
+    Value(ParseError),
+}
+
+impl fmt::Display for PatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PatchError::NotFound => write!(f, "nothing to replace at that path"),
+            PatchError::Document(error) => write!(f, "invalid document: {}", error),
+            PatchError::Value(error) => write!(f, "invalid replacement: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for PatchError {}
+
+/// Replaces the value at the query's path with the JSON text [value].
+/// The path must exist and its filters are ignored.
+pub fn set(json: &[u8], query: &Query, value: &[u8]) -> Result<Vec<u8>, PatchError> {
+    JSONReader::new(json).check().map_err(PatchError::Document)?;
+    JSONReader::new(value).check().map_err(PatchError::Value)?;
+    let (_, found): (Option<JSONRange>, JSONValue) = engine::resolve(json, query).ok_or(PatchError::NotFound)?;
+    let (start, end): JSONRange = found.extent();
+    // only the value itself, without the whitespace or byte order mark check() allows around it
+    let root: JSONValue = JSONReader::new(value).read_value()
+        .ok_or(PatchError::Value(ParseError{ kind: ErrorKind::UnexpectedEnd, offset: value.len() }))?;
+    let (from, to): JSONRange = root.extent();
+    let value: &[u8] = &value[from..to];
+    let mut patched: Vec<u8> = Vec::with_capacity(json.len() - (end - start) + value.len());
+    patched.extend_from_slice(&json[..start]);
+    patched.extend_from_slice(value);
+    patched.extend_from_slice(&json[end..]);
+    Ok(patched)
+}
+
+/// Replaces the value at the query's path with [number], written out exactly
+pub fn set_number(json: &[u8], query: &Query, number: &Decimal) -> Result<Vec<u8>, PatchError> {
+    set(json, query, number.to_string().as_bytes())
+}
+
+/// Applies every edit in order, each one seeing the result of those before it
+pub fn patch(json: &[u8], edits: &[(Query, &[u8])]) -> Result<Vec<u8>, PatchError> {
+    let mut patched: Vec<u8> = json.to_vec();
+    for (query, value) in edits {
+        patched = set(&patched, query, value)?;
+    }
+    Ok(patched)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::decimal::Decimal;
+    use crate::parser::{ErrorKind, ParseError};
+    use crate::patch::{patch, set, set_number, PatchError};
+    use crate::query::Query;
+    use crate::reader::JSONReader;
+    use crate::validation::Validator;
+
+    const JSON: &[u8] = br#"{"amount": 12345678901234567890.000001, "fee": 0.10, "memo": "a \"b\"", "lines": [1e400, 2]}"#;
+
+    #[test]
+    fn test_set() {
+        assert_eq!(set(JSON, &Query::from("fee"), b" 0.25 ").unwrap(),
+                   br#"{"amount": 12345678901234567890.000001, "fee": 0.25, "memo": "a \"b\"", "lines": [1e400, 2]}"#.to_vec());
+        assert_eq!(set(JSON, &Query::from("memo"), br#"{"x": null}"#).unwrap(),
+                   br#"{"amount": 12345678901234567890.000001, "fee": 0.10, "memo": {"x": null}, "lines": [1e400, 2]}"#.to_vec());
+        let amount: Decimal = "98765432109876543210.123456789".parse().unwrap();
+        let patched: Vec<u8> = set_number(JSON, &Query::from("lines.[1]"), &amount).unwrap();
+        assert_eq!(patched, br#"{"amount": 12345678901234567890.000001, "fee": 0.10, "memo": "a \"b\"", "lines": [1e400, 98765432109876543210.123456789]}"#.to_vec());
+        // a byte order mark is fine on a document of its own, but not in the middle of one
+        let patched: Vec<u8> = set(b"{\"a\": 1}", &Query::from("a"), b"\xEF\xBB\xBF \"2\"\n").unwrap();
+        assert_eq!(patched, b"{\"a\": \"2\"}".to_vec());
+        assert!(JSONReader::new(&patched).check().is_ok());
+    }
+
+    #[test]
+    fn test_set_errors() {
+        assert_eq!(set(JSON, &Query::from("missing"), b"1"), Err(PatchError::NotFound));
+        assert_eq!(set(JSON, &Query::from("fee"), b"1 2"), Err(PatchError::Value(ParseError{ kind: ErrorKind::TrailingCharacters, offset: 2 })));
+        assert!(matches!(set(b"{\"fee\": 1", &Query::from("fee"), b"2"), Err(PatchError::Document(_))));
+    }
+
+    #[test]
+    fn test_patch() {
+        let edits: Vec<(Query, &[u8])> = vec!((Query::from("lines.[0]"), b"3"), (Query::from("lines"), b"[]"), (Query::from("fee"), b"-0.000"));
+        assert_eq!(patch(JSON, &edits).unwrap(),
+                   br#"{"amount": 12345678901234567890.000001, "fee": -0.000, "memo": "a \"b\"", "lines": []}"#.to_vec());
+        assert_eq!(patch(JSON, &[(Query::from("lines"), b"[]"), (Query::from("lines.[0]"), b"3")]), Err(PatchError::NotFound));
+    }
+}
@@ -0,0 +1,179 @@
+//! The inverse of extraction: from a byte offset, e.g. an editor cursor or the offset of
+//! an error, back to the path of the value containing it.
+//!
+//! The walk follows the parser's tokens, keeping the key or index of every open
+//! container, and stops as soon as the innermost value around the offset is closed.
+//! An offset on a key belongs to that key's value, one on whitespace or punctuation
+//! between members belongs to the enclosing container.
+
+use std::fmt;
+use crate::limits::Limits;
+use crate::parser::{Event, ParseError, Parser, Token};
+use crate::reader::{JSONRange, JSONReader, JSONType, JSONValue};
+use crate::string;
+
+/// One step down from a container
+#[derive(Debug, PartialEq, Clone)]
+pub enum Step {
+    /// A member of an object, its key with escapes resolved
+    Key(String),
+    /// An element of an array
+    Index(usize),
+}
+
+/// The steps from the root to a value, empty for the root itself
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Path {
+    pub steps: Vec<Step>,
+}
+
+impl Path {
+    /// The path in the query syntax, e.g. `hobbies.[1]`, ready for [crate::query::Query::from].
+    /// Keys containing `.`, `|` or brackets cannot be expressed there and come out as written.
+    pub fn to_query(&self) -> String {
+        self.steps.iter()
+            .map(|step| match step {
+                Step::Key(key) => key.clone(),
+                Step::Index(index) => format!("[{}]", index),
+            })
+            .collect::<Vec<String>>()
+            .join(".")
+    }
+}
+
+impl fmt::Display for Path {
+    /// Writes the path like `nested.herp` or `hobbies[1]`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (idx, step) in self.steps.iter().enumerate() {
+            match step {
+                Step::Key(key) if idx == 0 => write!(f, "{}", key)?,
+                Step::Key(key) => write!(f, ".{}", key)?,
+                Step::Index(index) => write!(f, "[{}]", index)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// An open container: what leads to it, and what the next value inside it is called
+struct Frame {
+    json_type: JSONType,
+    start: usize,
+    elements: usize,
+    key: Option<String>,
+}
+
+fn decode_key(json: &[u8], range: JSONRange) -> String {
+    let raw: &[u8] = &json[range.0..range.1];
+    match string::decode(raw) {
+        Ok(key) => key.into_owned(),
+        Err(_) => String::from_utf8_lossy(raw).into_owned()
+    }
+}
+
+/// The path of the innermost value of [json] whose bytes contain [offset], strings and
+/// keys including their quotes. None if [offset] lies outside the root value. Only the
+/// document up to the end of that value has to be valid, and no limits apply to it.
+pub fn path_at(json: &[u8], offset: usize) -> Result<Option<Path>, ParseError> {
+    let mut stack: Vec<Frame> = vec!();
+    let mut path: Path = Path::default();
+    let mut reader: JSONReader = JSONReader::new(json);
+    for token in Parser::new(&mut reader).with_limits(Limits::unlimited()) {
+        let token: Token = token?;
+        let json_type: JSONType = match token.event {
+            Event::Key => {
+                let key: String = decode_key(json, token.range);
+                if (token.range.0 - 1..=token.range.1).contains(&offset) {
+                    path.steps.push(Step::Key(key));
+                    return Ok(Some(path))
+                }
+                if let Some(frame) = stack.last_mut() {
+                    frame.key = Some(key);
+                }
+                continue
+            },
+            Event::EndObject | Event::EndArray => {
+                let frame: Option<Frame> = stack.pop();
+                if frame.is_some_and(|frame| (frame.start..token.range.1).contains(&offset)) {
+                    return Ok(Some(path))
+                }
+                path.steps.pop();
+                continue
+            },
+            Event::StartObject => JSONType::OBJECT,
+            Event::StartArray => JSONType::ARRAY,
+            Event::Value(json_type) => json_type,
+        };
+        let step: Option<Step> = stack.last_mut().map(|frame| match frame.json_type {
+            JSONType::OBJECT => Step::Key(frame.key.take().unwrap_or_default()),
+            _ => {
+                frame.elements += 1;
+                Step::Index(frame.elements - 1)
+            }
+        });
+        path.steps.extend(step);
+        if json_type.is_scalar() {
+            let extent: JSONRange = JSONValue::new(json_type, token.range).extent();
+            if (extent.0..extent.1).contains(&offset) {
+                return Ok(Some(path))
+            }
+            if stack.is_empty() {
+                return Ok(None) // a scalar root that does not contain it
+            }
+            path.steps.pop();
+        } else {
+            if stack.is_empty() && offset < token.range.0 {
+                return Ok(None)
+            }
+            stack.push(Frame{ json_type, start: token.range.0, elements: 0, key: None });
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::engine::select;
+    use crate::parser::ErrorKind;
+    use crate::path::{path_at, Path, Step};
+    use crate::query::Query;
+
+    const JSON: &[u8] = r#"{"name":"alex","hobbies":["cooking","guitar"],"nested":{"foo":"bar","herp":false}}"#.as_bytes();
+
+    fn path(offset: usize) -> Option<String> {
+        path_at(JSON, offset).unwrap().map(|path| path.to_string())
+    }
+
+    #[test]
+    fn test_path_at() {
+        assert_eq!(path(0), Some("".to_string()));
+        assert_eq!(path(1), Some("name".to_string())); // the key's opening quote
+        assert_eq!(path(10), Some("name".to_string()));
+        assert_eq!(path(14), Some("".to_string())); // the comma between members
+        assert_eq!(path(25), Some("hobbies".to_string()));
+        assert_eq!(path(26), Some("hobbies[0]".to_string()));
+        assert_eq!(path(38), Some("hobbies[1]".to_string()));
+        assert_eq!(path(77), Some("nested.herp".to_string()));
+        assert_eq!(path(80), Some("nested".to_string()));
+        assert_eq!(path(81), Some("".to_string()));
+        assert_eq!(path(82), None);
+        assert_eq!(path_at(b"  [1]", 0), Ok(None));
+        // a broken document still answers for offsets before it breaks
+        assert_eq!(path_at(b"[1, 2", 4).map(|path| path.map(|path| path.to_string())), Ok(Some("[1]".to_string())));
+        assert_eq!(path_at(b"[1, 2", 5).unwrap_err().kind, ErrorKind::UnexpectedEnd);
+        let deep: Vec<u8> = [vec!(b'['; 200), vec!(b']'; 200)].concat();
+        assert_eq!(path_at(&deep, 199).map(|path| path.map(|path| path.steps.len())), Ok(Some(199)));
+        assert_eq!(path_at(br#"{"a.b": [{"c": 1}]}"#, 15).unwrap().unwrap().steps,
+                   vec!(Step::Key("a.b".to_string()), Step::Index(0), Step::Key("c".to_string())));
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for offset in 0..JSON.len() {
+            let path: Path = path_at(JSON, offset).unwrap().unwrap();
+            let query: String = path.to_query();
+            let start: usize = select(JSON, &Query::from(&query))[0].range().0;
+            assert_eq!(path_at(JSON, start), Ok(Some(path)), "{}", offset);
+        }
+    }
+}
@@ -1,3 +1,5 @@
+use crate::reader::JSONType;
+
 /// This module models out the query language for gsjf.
 ///
 /// Every query is separated by a | character
@@ -9,17 +11,24 @@
 ///     - [1] selects the first (0-indexed) element
 ///     - []
 ///
+/// Type filters follow the path after a |, keeping only results of that type.
+/// When the path selects an array, the filter applies to each of its elements:
+///     - strings, numbers, objects, arrays, booleans, nulls
+///     - scalars keeps everything that is not an array or object
+///
 /// Some query examples:
 /// key => value
 /// key.key => value
 /// key.[1] => first element of an array
 /// [1] => first element of the array
+/// key | strings => the strings in the array at key
 ///
 
 #[derive(Debug, PartialEq)]
 pub struct Query<'a> {
     raw: &'a [u8],
     pub(crate) components: Vec<QueryComponent<'a>>,
+    pub(crate) filters: Vec<Filter>,
 }
 
 #[derive(Debug, PartialEq)]
@@ -27,13 +36,60 @@ pub struct QueryComponent<'a> {
     pub(crate) path: &'a [u8],
 }
 
+/// Keeps only the results of a query of a given type
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Filter {
+    Type(JSONType),
+    Scalars,
+}
+
+impl Filter {
+    /// Looks up a filter by its name in a query, e.g. `strings`
+    pub fn from_name(name: &str) -> Option<Filter> {
+        match name {
+            "strings" => Some(Filter::Type(JSONType::STRING)),
+            "numbers" => Some(Filter::Type(JSONType::NUMBER)),
+            "objects" => Some(Filter::Type(JSONType::OBJECT)),
+            "arrays" => Some(Filter::Type(JSONType::ARRAY)),
+            "booleans" => Some(Filter::Type(JSONType::BOOLEAN)),
+            "nulls" => Some(Filter::Type(JSONType::NULL)),
+            "scalars" => Some(Filter::Scalars),
+            _ => None
+        }
+    }
+
+    pub fn matches(&self, json_type: JSONType) -> bool {
+        match self {
+            Filter::Type(expected) => *expected == json_type,
+            Filter::Scalars => json_type.is_scalar(),
+        }
+    }
+}
+
+impl QueryComponent<'_> {
+    /// The index of an array selection such as [1], None for key selections
+    pub fn index(&self) -> Option<usize> {
+        let inner: &[u8] = self.path.strip_prefix(b"[")?.strip_suffix(b"]")?;
+        std::str::from_utf8(inner).ok()?.parse().ok()
+    }
+}
+
 impl<'a> Query<'a> {
+    /// Unrecognised filters are ignored
     pub fn from(raw: &'a str) -> Query<'a> {
+        let mut pieces = raw.split('|');
+        let path: &str = pieces.next().unwrap_or("").trim();
         let mut components: Vec<QueryComponent> = vec!();
-        for piece in raw.split(".") {
+        for piece in path.split('.') {
             components.push(QueryComponent{ path: piece.as_bytes() });
         }
-        Query{ raw: raw.as_bytes(), components }
+        let filters: Vec<Filter> = pieces.filter_map(|name| Filter::from_name(name.trim())).collect();
+        Query{ raw: raw.as_bytes(), components, filters }
+    }
+
+    /// Whether a value of [json_type] passes every filter of the query
+    pub fn keeps(&self, json_type: JSONType) -> bool {
+        self.filters.iter().all(|filter| filter.matches(json_type))
     }
 
     // fn empty() -> Query<'a> {
@@ -49,13 +105,33 @@ impl<'a> Query<'a> {
 ///     - ?: matches any character
 ///     - [] matches characters in here
 /// TODO: just use rust glob? it probably exists: https://research.swtch.com/glob
-fn glob(query: &[u8], candidate: &[u8]) -> bool {
+#[allow(dead_code)]
+fn glob(_query: &[u8], _candidate: &[u8]) -> bool {
     false
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::query::Query;
+    use crate::query::{Filter, Query};
+    use crate::reader::JSONType;
+
+    #[test]
+    fn filters() {
+        let query: Query = Query::from("hobbies | strings");
+        assert_eq!(query.components.len(), 1);
+        assert_eq!(query.components[0].path, b"hobbies");
+        assert_eq!(query.filters, vec!(Filter::Type(JSONType::STRING)));
+        assert!(query.keeps(JSONType::STRING));
+        assert!(!query.keeps(JSONType::NUMBER));
+        assert_eq!(Query::from("| scalars | nonsense").filters, vec!(Filter::Scalars));
+    }
+
+    #[test]
+    fn index() {
+        let query: Query = Query::from("hobbies.[1]");
+        assert_eq!(query.components[0].index(), None);
+        assert_eq!(query.components[1].index(), Some(1));
+    }
 
     #[test]
     fn single_path() {
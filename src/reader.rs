@@ -1,10 +1,17 @@
-use std::borrow::Borrow;
+use crate::duplicates::DuplicateKeys;
+use crate::iter;
+use crate::limits::Limits;
+use crate::parser::{Event, ParseError, Parser, Token};
+use crate::string::KeyMatch;
 
 pub trait Reader {
 
     /// Returns the current position of the [Reader]
     fn position(&mut self) -> usize;
 
+    /// Returns the offset of the next token, i.e. how many tokens have been read
+    fn offset(&self) -> usize;
+
     /// Next retrieves the next token, or None if nothing is available
     /// The position will be moved forward
     fn next(&mut self) -> Option<u8>;
@@ -21,189 +28,100 @@ pub trait Reader {
     /// Retrieves the current token, does not advance the position
     fn current(&self) -> Option<u8>;
 
-    /// Reads a &str
-    fn read_string(&mut self) -> Option<(usize, usize)>;
-
-    fn read_number(&mut self) -> Option<JSONRange>;
-
-    /// Reads a JSON object (array | object)
-    fn read_json(&mut self) -> Option<(usize, usize)>;
-
-    /// Reads a known number of bytes (for things like null, false, true, etc)
-    fn read_known(&mut self, bytes: usize) -> Option<(usize, usize)>;
-
-    /// Finds a key in the current level of the JSON object
-    fn find_key(&mut self, key: &[u8]) -> Option<(usize, usize)>;
-
-    fn read_value(&mut self) -> Option<JSONValue>;
-
     /// Seeks to the provided position
     fn seek(&mut self, offset: usize);
 
-    /// Resets to the beginning
-    fn reset(&mut self);
-
-    /// Scans through an open-close pair and returns the end position
-    /// Example: reader.scan(b'{', b'}') scans through a JSON object
-    fn scan(&mut self, open: u8, close: u8) -> Option<usize>;
-
-    /// Skips ahead to the next occurrence of [val]
-    fn skip_to_next(&mut self, val: u8) -> Option<usize>;
-
-    fn skip_to_previous(&mut self, val: u8) -> Option<usize>;
-
-    fn skip_past_whitespace(&mut self);
-
     fn print_at(&self, start: usize, end: usize);
 
     fn select(&mut self, range: JSONRange) -> Option<&[u8]>;
-}
-
-pub struct JSONValue {
-    pub(crate) json_type: JSONType,
-    pub(crate) range: JSONRange,
-}
 
-impl JSONValue {
-    pub fn new(json_type: JSONType, range: (usize, usize)) -> JSONValue {
-        JSONValue{ json_type, range, }
-    }
-}
-
-pub enum JSONType {
-    ARRAY,
-    OBJECT,
-    NULL,
-    STRING,
-    BOOLEAN,
-    NUMBER
-}
-
-pub struct JSONReader<'a> {
-    data: &'a [u8],
-    offset: usize,
-}
-
-impl JSONReader<'_> {
-    pub fn new(data: &[u8]) -> JSONReader {
-        JSONReader{data, offset: 0}
-    }
-}
-
-type JSONRange = (usize, usize);
-
-impl Reader for JSONReader<'_> {
-
-    fn position(&mut self) -> usize {
-        match self.offset {
-            0 => 0,
-            _ => self.offset - 1
-        }
-    }
-
-    fn next(&mut self) -> Option<u8> {
-        if self.offset + 1 == self.data.len() {
-            return None
-        }
-        let next: u8 = self.data[self.offset];
-        self.offset += 1;
-        Some(next)
-    }
-
-    fn prev(&mut self) -> Option<u8> {
-        if self.offset == 0 {
-            return None
-        }
-        let prev: u8 = self.data[self.offset - 1];
-        self.offset -= 1;
-        Some(prev)
-    }
-
-    fn peek(&mut self) -> Option<u8> {
-        if self.offset + 1 == self.data.len() {
-            return None
-        }
-        Some(self.data[self.offset])
-    }
-
-    fn last(&self) -> Option<u8> {
-        if self.offset == 0 {
-            return None
-        }
-        Some(self.data[self.offset - 1])
-    }
-
-    fn current(&self) -> Option<u8> {
-        let token: u8 = match self.offset {
-            0 => self.data[0],
-            _ => self.data[self.offset - 1]
-        };
-        Some(token)
-    }
+    /// Hints that nothing before [offset] will be seeked to or selected again.
+    /// Readers that hold the whole document can ignore this.
+    fn release(&mut self, _offset: usize) {}
 
+    /// Reads a &str
     fn read_string(&mut self) -> Option<(usize, usize)> {
-        let start: usize = self.offset;
+        let start: usize = self.offset();
         while let Some(token) = self.next() {
             match token {
-                b'"' => break,
+                // the closing quote is excluded
+                b'"' => return Some((start, self.offset() - 1)),
                 b'\\' => self.next(), // advance one more position as it's an escaped quote
                 _ => None
             };
         }
-        Some((start, self.offset - 1)) // TODO: these -1's seem off
+        Some((start, self.offset())) // never terminated, runs to the end
     }
 
     fn read_number(&mut self) -> Option<JSONRange> {
-        None
+        let start: usize = self.offset().checked_sub(1)?;
+        while let Some(b'0'..=b'9') | Some(b'-') | Some(b'+') | Some(b'.') | Some(b'e') | Some(b'E') = self.peek() {
+            self.next();
+        }
+        Some((start, self.offset()))
     }
 
+    /// Reads a JSON object (array | object)
     fn read_json(&mut self) -> Option<(usize, usize)> {
-        let start: usize = self.offset - 1; // TODO: these -1's seem off
+        let start: usize = self.offset().checked_sub(1)?;
         let end: Option<usize> = match self.current() {
             Some(b'{') => self.scan(b'{', b'}'),
             Some(b'[') => self.scan(b'[', b']'),
             _ => None
         };
-        match end {
-            None => None,
-            Some(index) => Some((start, index))
-        }
+        end.map(|index| (start, index))
     }
 
+    /// Reads a known number of bytes (for things like null, false, true, etc)
     fn read_known(&mut self, bytes: usize) -> Option<(usize, usize)> {
-        if self.offset + bytes > self.data.len() {
-            return None
+        let start: usize = self.offset().checked_sub(1)?;
+        for _ in 1..bytes {
+            self.next()?;
         }
-        let start = self.offset - 1; // todo: feels gross? use self.position()
-        self.seek(start + bytes);
-        Some((start, self.offset))
+        Some((start, self.offset()))
     }
 
+    /// Finds a key in the current level of the JSON object
     fn find_key(&mut self, key: &[u8]) -> Option<(usize, usize)> {
-        let mut hit_first_json: bool = false;
-        while let Some(token) = self.next() {
-            match token {
-                b'{' | b'[' => {
-                    if !hit_first_json {
-                        hit_first_json = true;
-                        continue;
-                    }
-                    if let None = self.read_json() {
-                        // TODO: test case?
-                        return None; // never ended, mis-formatted JSON
-                    }
-                },
-                b'"' => {
-                    let found: (usize, usize) = self.read_string().unwrap();
-                    let extracted: &[u8] = self.data[found.0 .. found.1].borrow(); // TODO: give this a method
-                    if extracted == key {
-                        return Some(found);
-                    }
-                },
-                _ => {}
-            };
-        };
-        None
+        self.find_key_with(key, KeyMatch::Exact)
+    }
+
+    /// Finds a key in the current level of the JSON object, comparing keys as [matching] describes.
+    /// Leaves the reader just before the key's value, ready for [Reader::read_value].
+    /// Members are read with a [Parser], so a malformed member before the key ends the lookup.
+    fn find_key_with(&mut self, key: &[u8], matching: KeyMatch) -> Option<JSONRange> {
+        let mut parser: Parser<Self> = object_parser(self)?;
+        let found: JSONRange = next_matching_key(&mut parser, key, matching)?;
+        past_colon(self, found)
+    }
+
+    /// Finds a key like [Reader::find_key], settling a key that repeats as [policy] says.
+    /// Keys are compared with their escapes resolved, the way the validator sees duplicates.
+    /// Anything but [DuplicateKeys::FirstWins] reads the whole level of the object.
+    fn find_key_with_policy(&mut self, key: &[u8], policy: DuplicateKeys) -> Result<Option<JSONRange>, ParseError> {
+        self.find_key_matching(key, KeyMatch::Decoded, policy)
+    }
+
+    /// [Reader::find_key_with_policy], comparing keys as [matching] describes.
+    /// [KeyMatch::Exact] is compared as [KeyMatch::Decoded] since policies decide on decoded keys.
+    fn find_key_matching(&mut self, key: &[u8], matching: KeyMatch, policy: DuplicateKeys) -> Result<Option<JSONRange>, ParseError> {
+        let matching: KeyMatch = matching.decoded();
+        if policy == DuplicateKeys::FirstWins {
+            return Ok(self.find_key_with(key, matching))
+        }
+        let mut found: Vec<(usize, JSONRange)> = vec!();
+        if let Some(mut parser) = object_parser(self) {
+            while let Some(range) = next_matching_key(&mut parser, key, matching) {
+                found.push((range.0, range));
+            }
+        }
+        match policy.resolve(found)? {
+            None => Ok(None),
+            Some(range) => {
+                self.seek(range.1 + 1); // just past the closing quote
+                Ok(past_colon(self, range))
+            }
+        }
     }
 
     /// Assumes we're on a " after an object key
@@ -232,45 +150,59 @@ impl Reader for JSONReader<'_> {
         None
     }
 
-    fn seek(&mut self, offset: usize) {
-        self.offset = offset
+    /// Collects the ranges of every key in the current level of the JSON object
+    fn keys(&mut self) -> Vec<JSONRange> {
+        let mut keys: Vec<JSONRange> = vec!();
+        let mut started: bool = false;
+        while let Some(key) = iter::next_key(self, &mut started) {
+            keys.push(key);
+            if self.read_value().is_none() {
+                break
+            }
+        }
+        keys
     }
 
+    /// Resets to the beginning
     fn reset(&mut self) {
         self.seek(0)
     }
-    
+
+    /// Scans through an open-close pair and returns the end position
+    /// Example: reader.scan(b'{', b'}') scans through a JSON object
+    ///
     /// This function does not assume that you are already on an [open] character,
     /// it will first check if you are. If you are not, it assumes you are inside of an [open]
     fn scan(&mut self, open: u8, close: u8) -> Option<usize> {
         match self.current() {
             None => return None,
             Some(token) => {
-                if token != open {
-                    if self.skip_to_previous(open) == None {
-                        return None
-                    }
+                if token != open && self.skip_to_previous(open).is_none() {
+                    return None
                 }
             }
         }
-        let mut depth: u8 = 1;
+        let mut depth: usize = 1;
         while let Some(token) = self.next() {
-            if token == open {
+            if token == b'"' {
+                self.read_string()?; // brackets inside strings don't count
+            } else if token == open {
                 depth += 1;
             } else if token == close {
                 depth -= 1;
                 if depth == 0 {
-                    return Some(self.offset)
+                    return Some(self.offset())
                 }
             }
         }
         None
     }
 
+    /// Skips ahead to the next occurrence of [val]
     fn skip_to_next(&mut self, val: u8) -> Option<usize> {
         while let Some(token) = self.next() {
             if token == val {
-                return Some(self.offset)
+                return Some(self.offset())
             }
         }
         None
@@ -279,45 +211,229 @@ impl Reader for JSONReader<'_> {
     fn skip_to_previous(&mut self, val: u8) -> Option<usize> {
         while let Some(token) = self.prev() {
             if token == val {
-                return Some(self.offset);
+                return Some(self.offset());
             }
         }
         None
     }
 
+    /// Skips ahead until the next token is not whitespace
     fn skip_past_whitespace(&mut self) {
-        match self.peek() {
-            None => return,
-            Some(token) => {
-                match token {
-                    b' ' | b'\t' | b'\n' | b'\r' => {
-                        self.next();
-                    },
-                    _ => return
-                }
-            }
+        while let Some(b' ') | Some(b'\t') | Some(b'\n') | Some(b'\r') = self.peek() {
+            self.next();
+        }
+    }
+}
+
+/// A parser over the object the reader is positioned on, past its opening brace.
+/// None if the next value is not an object. Lookups are not bound by [Limits::default],
+/// they only read the document, the way [Reader::read_value] always has.
+fn object_parser<R: Reader + ?Sized>(reader: &mut R) -> Option<Parser<'_, R>> {
+    let mut parser: Parser<R> = Parser::new(reader).with_limits(Limits::unlimited());
+    match parser.next() {
+        Some(Ok(Token{ event: Event::StartObject, .. })) => Some(parser),
+        _ => None
+    }
+}
+
+/// Reads members until a key of the object's own level matches [key], None once the
+/// object ends or turns out malformed. The reader is left just past the key's closing quote.
+fn next_matching_key<R: Reader + ?Sized>(parser: &mut Parser<R>, key: &[u8], matching: KeyMatch) -> Option<JSONRange> {
+    while let Some(Ok(token)) = parser.next() {
+        match token.event {
+            Event::Key if token.depth == 1 && parser.reader().select(token.range).is_some_and(|raw| matching.matches(raw, key)) => {
+                return Some(token.range)
+            },
+            Event::EndObject if token.depth == 0 => return None,
+            _ => ()
         }
     }
+    None
+}
+
+/// Consumes the colon after the key at [found], leaving the reader just before its value
+fn past_colon<R: Reader + ?Sized>(reader: &mut R, found: JSONRange) -> Option<JSONRange> {
+    reader.skip_past_whitespace();
+    match reader.next() {
+        Some(b':') => Some(found),
+        _ => None
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct JSONValue {
+    pub(crate) json_type: JSONType,
+    pub(crate) range: JSONRange,
+}
+
+impl JSONValue {
+    pub fn new(json_type: JSONType, range: (usize, usize)) -> JSONValue {
+        JSONValue{ json_type, range, }
+    }
+
+    pub fn json_type(&self) -> JSONType {
+        self.json_type
+    }
 
+    /// The byte range of the value, strings exclude their quotes
+    pub fn range(&self) -> JSONRange {
+        self.range
+    }
+
+    /// The bytes the value takes up, strings with their quotes. Saturates rather than
+    /// panicking on a value that was not read from a document.
+    pub fn extent(&self) -> JSONRange {
+        match self.json_type {
+            JSONType::STRING => (self.range.0.saturating_sub(1), self.range.1.saturating_add(1)),
+            _ => self.range
+        }
+    }
+}
+
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum JSONType {
+    ARRAY,
+    OBJECT,
+    NULL,
+    STRING,
+    BOOLEAN,
+    NUMBER
+}
+
+impl JSONType {
+    /// Scalars are every type that is not a container (array | object)
+    pub fn is_scalar(&self) -> bool {
+        !matches!(self, JSONType::ARRAY | JSONType::OBJECT)
+    }
+}
+
+#[derive(Clone)]
+pub struct JSONReader<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl JSONReader<'_> {
+    pub fn new(data: &[u8]) -> JSONReader<'_> {
+        JSONReader{data, offset: 0}
+    }
+}
+
+pub type JSONRange = (usize, usize);
+
+impl Reader for JSONReader<'_> {
+
+    fn position(&mut self) -> usize {
+        match self.offset {
+            0 => 0,
+            _ => self.offset - 1
+        }
+    }
+
+    fn offset(&self) -> usize {
+        self.offset
+    }
+
+    fn next(&mut self) -> Option<u8> {
+        if self.offset >= self.data.len() {
+            return None
+        }
+        let next: u8 = self.data[self.offset];
+        self.offset += 1;
+        Some(next)
+    }
+
+    fn prev(&mut self) -> Option<u8> {
+        if self.offset == 0 {
+            return None
+        }
+        let prev: u8 = self.data[self.offset - 1];
+        self.offset -= 1;
+        Some(prev)
+    }
+
+    fn peek(&mut self) -> Option<u8> {
+        if self.offset >= self.data.len() {
+            return None
+        }
+        Some(self.data[self.offset])
+    }
+
+    fn last(&self) -> Option<u8> {
+        if self.offset == 0 {
+            return None
+        }
+        Some(self.data[self.offset - 1])
+    }
+
+    fn current(&self) -> Option<u8> {
+        match self.offset {
+            0 => self.data.first().copied(),
+            _ => self.data.get(self.offset - 1).copied()
+        }
+    }
+
+    fn seek(&mut self, offset: usize) {
+        self.offset = offset
+    }
 
     fn print_at(&self, start: usize, end: usize) {
-        println!("printing: '{}'", std::str::from_utf8(self.data[start..end].borrow()).unwrap())
+        if let Some(bytes) = self.data.get(start..end) {
+            println!("printing: '{}'", String::from_utf8_lossy(bytes))
+        }
     }
 
     fn select(&mut self, range: (usize, usize)) -> Option<&[u8]> {
-        Some(self.data[range.0..range.1].borrow())
+        self.data.get(range.0..range.1)
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::reader::{JSONReader, Reader};
+    use crate::reader::{JSONReader, JSONType, JSONValue, Reader};
+    use crate::string::KeyMatch;
 
     const JSON: &[u8] = r#"{"name":"alex","boy":true,"age":32,"hobbies":["cooking","guitar"],"nested":{"foo":"bar"}}"#.as_bytes();
 
     #[test]
     fn test_find_key() {
-        assert_eq!(JSONReader::new(JSON).find_key("boy".as_bytes()), Some((16, 19)))
+        assert_eq!(JSONReader::new(JSON).find_key("boy".as_bytes()), Some((16, 19)));
+        assert_eq!(JSONReader::new(JSON).find_key(b"foo"), None); // only the object's own level
+        assert_eq!(JSONReader::new(br#"{"a": tru, "b": 1}"#).find_key(b"b"), None);
+        assert_eq!(JSONReader::new(b"[1]").find_key(b"a"), None);
+        let mut reader: JSONReader = JSONReader::new(br#" {"a" : [1]}"#);
+        assert_eq!(reader.find_key(b"a"), Some((3, 4)));
+        assert_eq!(reader.read_value(), Some(JSONValue::new(JSONType::ARRAY, (8, 11))));
+    }
+
+    #[test]
+    fn test_extent() {
+        assert_eq!(JSONValue::new(JSONType::STRING, (9, 13)).extent(), (8, 14));
+        assert_eq!(JSONValue::new(JSONType::ARRAY, (45, 65)).extent(), (45, 65));
+        assert_eq!(JSONValue::new(JSONType::STRING, (0, usize::MAX)).extent(), (0, usize::MAX));
+    }
+
+    #[test]
+    fn test_find_key_with() {
+        let json: &[u8] = br#"{"UserID": 1, "\u0075serId": 2}"#;
+        assert_eq!(JSONReader::new(json).find_key_with(b"userId", KeyMatch::Exact), None);
+        assert_eq!(JSONReader::new(json).find_key_with(b"userId", KeyMatch::Decoded), Some((15, 26)));
+        assert_eq!(JSONReader::new(json).find_key_with(b"userId", KeyMatch::CaseInsensitive), Some((2, 8)));
+    }
+
+    #[test]
+    fn test_keys() {
+        let mut reader = JSONReader::new(JSON);
+        let keys: Vec<&[u8]> = reader.keys().into_iter().map(|(start, end)| &JSON[start..end]).collect();
+        assert_eq!(keys, vec!(b"name".as_ref(), b"boy", b"age", b"hobbies", b"nested"));
+    }
+
+    #[test]
+    fn test_read_number() {
+        let mut reader = JSONReader::new(JSON);
+        reader.seek(33);
+        assert_eq!(reader.read_number(), Some((32, 34)))
     }
 
     #[test]
@@ -328,8 +444,19 @@ mod tests {
     }
 
     #[test]
-    fn test_read_string__escaped() {
-        // let mut reader: JSONReader = JSONReader::new("\"foo\"".as_bytes());
-        // assert_eq!(reader.read_string(), )
+    fn test_read_string_escaped() {
+        let mut reader: JSONReader = JSONReader::new(br#""fo\"o" "#);
+        reader.seek(1);
+        assert_eq!(reader.read_string(), Some((1, 6)))
+    }
+
+    #[test]
+    fn test_reads_at_the_edges() {
+        let mut reader: JSONReader = JSONReader::new(b"");
+        assert_eq!((reader.read_number(), reader.read_json(), reader.read_known(4)), (None, None, None));
+        assert_eq!(reader.select((0, 1)), None);
+        let mut reader: JSONReader = JSONReader::new(br#""ab"#);
+        reader.seek(1);
+        assert_eq!(reader.read_string(), Some((1, 3)))
     }
 }
\ No newline at end of file
@@ -1,12 +1,425 @@
-enum Type {
-    Object,
-    Array,
-    String,
+//! An owned tree of JSON values, for when a document is read once and then
+//! inspected or modified many times.
+
+use std::collections::HashMap;
+use crate::decimal::Decimal;
+use crate::duplicates::DuplicateKeys;
+use crate::lenient;
+use crate::limits::Limits;
+use crate::parser::Parser;
+use crate::query::Query;
+use crate::reader::{JSONRange, JSONReader, JSONType, Reader};
+use crate::string;
+use crate::validation::{self, Validator};
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Value {
+    /// Members are kept in the order they appear in the document
+    Object(Vec<(String, Value)>),
+    Array(Vec<Value>),
+    String(String),
+    /// The number exactly as it was written in the document
+    Number(String),
+    Bool(bool),
     Null,
-    Boolean(bool),
-    Number
 }
 
-struct JSONResult {
-    json_type: Type
-}
\ No newline at end of file
+impl Value {
+    /// Parses a complete document within the default [Limits]
+    pub fn parse(json: &[u8]) -> Option<Value> {
+        Value::parse_with_limits(json, Limits::default())
+    }
+
+    /// Parses a JSONC or JSON5 document within the default [Limits], see [crate::lenient]
+    pub fn parse_lenient(json: &[u8]) -> Option<Value> {
+        Value::parse(&lenient::to_strict(json).ok()?)
+    }
+
+    /// Parses a complete document, None if it is invalid or goes past [limits]
+    pub fn parse_with_limits(json: &[u8], limits: Limits) -> Option<Value> {
+        Value::parse_with(json, limits, DuplicateKeys::FirstWins)
+    }
+
+    /// Parses a complete document, keeping one member per key as [duplicates] says.
+    /// None if it is invalid, goes past [limits] or repeats a key when that is an error.
+    pub fn parse_with(json: &[u8], limits: Limits, duplicates: DuplicateKeys) -> Option<Value> {
+        let mut reader: JSONReader = JSONReader::new(json);
+        for token in Parser::new(&mut reader).with_limits(limits) {
+            token.ok()?;
+        }
+        let mut reader: JSONReader = JSONReader::new(json);
+        let value: Value = Value::read_nested(&mut reader, limits.max_depth, duplicates)?;
+        reader.skip_past_whitespace();
+        if reader.peek().is_some() {
+            return None // trailing garbage after the document
+        }
+        Some(value)
+    }
+
+    /// Reads the next value from the reader, leaving it positioned just past that value.
+    /// Nesting deeper than the default [Limits::max_depth] is rejected rather than
+    /// risking the stack, and the first member of a repeated key is kept.
+    pub fn read<R: Reader>(reader: &mut R) -> Option<Value> {
+        Value::read_nested(reader, Limits::default().max_depth, DuplicateKeys::FirstWins)
+    }
+
+    /// Reads a value with at most [depth] more containers open inside it
+    fn read_nested<R: Reader>(reader: &mut R, depth: usize, duplicates: DuplicateKeys) -> Option<Value> {
+        reader.skip_past_whitespace();
+        match reader.next()? {
+            b'{' | b'[' if depth == 0 => None,
+            b'{' => Value::read_object(reader, depth - 1, duplicates),
+            b'[' => Value::read_array(reader, depth - 1, duplicates),
+            b'"' => Value::read_string(reader).map(Value::String),
+            b'-' | b'0'..=b'9' => {
+                let range = reader.read_number()?;
+                let lexeme: &[u8] = reader.select(range)?;
+                if !validation::is_number(lexeme) {
+                    return None // e.g. 01, 1. or 1e
+                }
+                Some(Value::Number(String::from_utf8(lexeme.to_vec()).ok()?))
+            },
+            b't' => Value::read_literal(reader, b"true", Value::Bool(true)),
+            b'f' => Value::read_literal(reader, b"false", Value::Bool(false)),
+            b'n' => Value::read_literal(reader, b"null", Value::Null),
+            _ => None
+        }
+    }
+
+    /// Assumes the opening { has been read. A repeated key is settled as [duplicates] says,
+    /// the last value of a key is kept where the key first appeared, as JavaScript's
+    /// JSON.parse does.
+    fn read_object<R: Reader>(reader: &mut R, depth: usize, duplicates: DuplicateKeys) -> Option<Value> {
+        let mut members: Vec<(String, Value)> = vec!();
+        let mut positions: HashMap<String, usize> = HashMap::new();
+        reader.skip_past_whitespace();
+        if reader.peek() == Some(b'}') {
+            reader.next();
+            return Some(Value::Object(members))
+        }
+        loop {
+            reader.skip_past_whitespace();
+            if reader.next()? != b'"' {
+                return None
+            }
+            let key: String = Value::read_string(reader)?;
+            reader.skip_past_whitespace();
+            if reader.next()? != b':' {
+                return None
+            }
+            let value: Value = Value::read_nested(reader, depth, duplicates)?;
+            match (positions.get(&key), duplicates) {
+                (None, _) => {
+                    positions.insert(key.clone(), members.len());
+                    members.push((key, value));
+                },
+                (Some(_), DuplicateKeys::Error) => return None,
+                (Some(_), DuplicateKeys::FirstWins) => {},
+                (Some(position), DuplicateKeys::LastWins) => members[*position].1 = value,
+            }
+            reader.skip_past_whitespace();
+            match reader.next()? {
+                b',' => continue,
+                b'}' => return Some(Value::Object(members)),
+                _ => return None
+            }
+        }
+    }
+
+    /// Assumes the opening [ has been read
+    fn read_array<R: Reader>(reader: &mut R, depth: usize, duplicates: DuplicateKeys) -> Option<Value> {
+        let mut elements: Vec<Value> = vec!();
+        reader.skip_past_whitespace();
+        if reader.peek() == Some(b']') {
+            reader.next();
+            return Some(Value::Array(elements))
+        }
+        loop {
+            elements.push(Value::read_nested(reader, depth, duplicates)?);
+            reader.skip_past_whitespace();
+            match reader.next()? {
+                b',' => continue,
+                b']' => return Some(Value::Array(elements)),
+                _ => return None
+            }
+        }
+    }
+
+    /// Assumes the opening " has been read
+    fn read_string<R: Reader>(reader: &mut R) -> Option<String> {
+        let start: usize = reader.offset();
+        if !reader.validate_string() {
+            return None // never terminated, a raw control character or a broken escape
+        }
+        let range: JSONRange = (start, reader.offset() - 1);
+        string::decode(reader.select(range)?).ok().map(|decoded| decoded.into_owned())
+    }
+
+    /// Assumes the first byte of [literal] has been read
+    fn read_literal<R: Reader>(reader: &mut R, literal: &[u8], value: Value) -> Option<Value> {
+        let range = reader.read_known(literal.len())?;
+        match reader.select(range)? == literal {
+            true => Some(value),
+            false => None
+        }
+    }
+
+    pub fn json_type(&self) -> JSONType {
+        match self {
+            Value::Object(_) => JSONType::OBJECT,
+            Value::Array(_) => JSONType::ARRAY,
+            Value::String(_) => JSONType::STRING,
+            Value::Number(_) => JSONType::NUMBER,
+            Value::Bool(_) => JSONType::BOOLEAN,
+            Value::Null => JSONType::NULL,
+        }
+    }
+
+    /// Looks up a key of an object, the first one wins if it appears more than once
+    pub fn key(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::Object(members) => members.iter().find(|(name, _)| name == key).map(|(_, value)| value),
+            _ => None
+        }
+    }
+
+    /// Looks up an element of an array
+    pub fn index(&self, index: usize) -> Option<&Value> {
+        match self {
+            Value::Array(elements) => elements.get(index),
+            _ => None
+        }
+    }
+
+    /// Follows the query's path, ignoring its filters
+    pub fn get(&self, query: &Query) -> Option<&Value> {
+        let mut value: &Value = self;
+        for selector in query.components.iter().filter(|selector| !selector.path.is_empty()) {
+            value = match selector.index() {
+                Some(index) => value.index(index)?,
+                None => value.key(std::str::from_utf8(selector.path).ok()?)?
+            };
+        }
+        Some(value)
+    }
+
+    /// Follows the query's path like [Value::get], for changing the value in place
+    pub fn get_mut(&mut self, query: &Query) -> Option<&mut Value> {
+        let mut value: &mut Value = self;
+        for selector in query.components.iter().filter(|selector| !selector.path.is_empty()) {
+            value = match (value, selector.index()) {
+                (Value::Array(elements), Some(index)) => elements.get_mut(index)?,
+                (Value::Object(members), None) => {
+                    let key: &str = std::str::from_utf8(selector.path).ok()?;
+                    members.iter_mut().find(|(name, _)| name == key).map(|(_, value)| value)?
+                },
+                _ => return None
+            };
+        }
+        Some(value)
+    }
+
+    /// Replaces the value at the query's path, returning the one it replaced.
+    /// The path must exist, filters are ignored.
+    pub fn set(&mut self, query: &Query, value: Value) -> Option<Value> {
+        self.get_mut(query).map(|existing| std::mem::replace(existing, value))
+    }
+
+    /// The exact value of a number, see [Decimal]
+    pub fn as_decimal(&self) -> Option<Decimal> {
+        match self {
+            Value::Number(lexeme) => Decimal::parse(lexeme.as_bytes()).ok(),
+            _ => None
+        }
+    }
+
+    /// Follows the query's path and applies its filters, the same way [crate::engine::select] does
+    pub fn select(&self, query: &Query) -> Vec<&Value> {
+        let value: &Value = match self.get(query) {
+            None => return vec!(),
+            Some(value) => value
+        };
+        if query.filters.is_empty() {
+            return vec!(value)
+        }
+        let candidates: Vec<&Value> = match value {
+            Value::Array(elements) => elements.iter().collect(),
+            _ => vec!(value)
+        };
+        candidates.into_iter().filter(|candidate| query.keeps(candidate.json_type())).collect()
+    }
+
+    /// Serialises the value as minified JSON
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out: Vec<u8> = vec!();
+        self.write(&mut out);
+        out
+    }
+
+    pub fn write(&self, out: &mut Vec<u8>) {
+        match self {
+            Value::Object(members) => {
+                out.push(b'{');
+                for (idx, (key, value)) in members.iter().enumerate() {
+                    if idx > 0 {
+                        out.push(b',');
+                    }
+                    write_string(key, out);
+                    out.push(b':');
+                    value.write(out);
+                }
+                out.push(b'}');
+            },
+            Value::Array(elements) => {
+                out.push(b'[');
+                for (idx, element) in elements.iter().enumerate() {
+                    if idx > 0 {
+                        out.push(b',');
+                    }
+                    element.write(out);
+                }
+                out.push(b']');
+            },
+            Value::String(string) => write_string(string, out),
+            Value::Number(lexeme) => out.extend_from_slice(lexeme.as_bytes()),
+            Value::Bool(true) => out.extend_from_slice(b"true"),
+            Value::Bool(false) => out.extend_from_slice(b"false"),
+            Value::Null => out.extend_from_slice(b"null"),
+        }
+    }
+}
+
+impl From<Decimal> for Value {
+    fn from(number: Decimal) -> Value {
+        Value::Number(number.to_string())
+    }
+}
+
+macro_rules! from_integer {
+    ($($integer:ty),*) => {
+        $(impl From<$integer> for Value {
+            fn from(number: $integer) -> Value {
+                Value::Number(number.to_string())
+            }
+        })*
+    };
+}
+
+from_integer!(i64, u64, i128, u128);
+
+/// Writes a quoted string, escaping what JSON requires to be escaped
+fn write_string(string: &str, out: &mut Vec<u8>) {
+    out.push(b'"');
+    for character in string.chars() {
+        match character {
+            '"' => out.extend_from_slice(b"\\\""),
+            '\\' => out.extend_from_slice(b"\\\\"),
+            '\n' => out.extend_from_slice(b"\\n"),
+            '\r' => out.extend_from_slice(b"\\r"),
+            '\t' => out.extend_from_slice(b"\\t"),
+            '\u{8}' => out.extend_from_slice(b"\\b"),
+            '\u{c}' => out.extend_from_slice(b"\\f"),
+            c if (c as u32) < 0x20 => out.extend_from_slice(format!("\\u{:04x}", c as u32).as_bytes()),
+            c => out.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes()),
+        }
+    }
+    out.push(b'"');
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::decimal::Decimal;
+    use crate::duplicates::DuplicateKeys;
+    use crate::limits::Limits;
+    use crate::query::Query;
+    use crate::reader::JSONReader;
+    use crate::result::Value;
+
+    const JSON: &[u8] = r#"{"name":"alex","boy":true,"age":32,"hobbies":["cooking","guitar"],"nested":{"foo":"bar","herp":false,"something":null}}"#.as_bytes();
+
+    #[test]
+    fn test_parse() {
+        let value: Value = Value::parse(JSON).unwrap();
+        assert_eq!(value.key("age"), Some(&Value::Number("32".to_string())));
+        assert_eq!(value.get(&Query::from("hobbies.[1]")), Some(&Value::String("guitar".to_string())));
+        assert_eq!(value.get(&Query::from("nested.herp")), Some(&Value::Bool(false)));
+        assert_eq!(value.get(&Query::from("nested.missing")), None);
+        assert_eq!(value.select(&Query::from("nested | objects")).len(), 1);
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        assert_eq!(Value::parse(br#"{"a": tru}"#), None);
+        assert_eq!(Value::parse(br#"{"a" 1}"#), None);
+        assert_eq!(Value::parse(br#"[1, 2"#), None);
+        assert_eq!(Value::parse(br#"[1] [2]"#), None);
+        for number in ["01", "1.", "-", "1e", "1e+", "--1", "1-2", ".5"] {
+            let mut reader: JSONReader = JSONReader::new(number.as_bytes());
+            assert_eq!(Value::read(&mut reader), None, "{}", number);
+        }
+        for string in [br#""ab\""#.as_ref(), b"\"tab\t\"", b"\"nul\x00\""] {
+            let mut reader: JSONReader = JSONReader::new(string);
+            assert_eq!(Value::read(&mut reader), None, "{}", String::from_utf8_lossy(string));
+        }
+        let mut reader: JSONReader = JSONReader::new(br#""ab\"" "#);
+        assert_eq!(Value::read(&mut reader), Some(Value::String("ab\"".to_string())));
+        let mut reader: JSONReader = JSONReader::new(b"[-0.5e+3]");
+        assert_eq!(Value::read(&mut reader), Some(Value::Array(vec!(Value::Number("-0.5e+3".to_string())))));
+    }
+
+    #[test]
+    fn test_parse_lenient() {
+        let value: Value = Value::parse_lenient(b"{name: 'alex', /* note */ ports: [0x50, +443,],}").unwrap();
+        assert_eq!(value.to_bytes(), br#"{"name":"alex","ports":[80,443]}"#.to_vec());
+        assert_eq!(Value::parse_lenient(b"{name: }"), None);
+    }
+
+    #[test]
+    fn test_parse_with_duplicates() {
+        let json: &[u8] = br#"{"a": 1, "b": [{"c": 1, "c": 2}], "a": 3}"#;
+        let parse = |duplicates: DuplicateKeys| Value::parse_with(json, Limits::default(), duplicates).map(|value| value.to_bytes());
+        assert_eq!(parse(DuplicateKeys::FirstWins), Some(br#"{"a":1,"b":[{"c":1}]}"#.to_vec()));
+        assert_eq!(parse(DuplicateKeys::LastWins), Some(br#"{"a":3,"b":[{"c":2}]}"#.to_vec()));
+        assert_eq!(parse(DuplicateKeys::Error), None);
+        assert_eq!(Value::parse(json).unwrap().key("a"), Some(&Value::Number("1".to_string())));
+        // a repeat found first does not hide a broken document after it
+        assert_eq!(Value::parse_with(br#"{"a": 1, "a": 2, }"#, Limits::default(), DuplicateKeys::LastWins), None);
+        let many: String = format!("{{{}\"k0\": true}}", (0..50_000).map(|idx| format!("\"k{}\": {},", idx, idx)).collect::<String>());
+        let value: Value = Value::parse_with(many.as_bytes(), Limits::default(), DuplicateKeys::LastWins).unwrap();
+        assert_eq!(value.key("k0"), Some(&Value::Bool(true)));
+        assert_eq!(value.key("k49999"), Some(&Value::Number("49999".to_string())));
+        // keys repeat once their escapes are resolved
+        assert_eq!(Value::parse_with(br#"{"a": 1, "\u0061": 2}"#, Limits::default(), DuplicateKeys::Error), None);
+        let mut reader: JSONReader = JSONReader::new(br#"{"a": 1, "\u0061": 2}"#);
+        assert_eq!(Value::read(&mut reader), Some(Value::Object(vec!(("a".to_string(), Value::Number("1".to_string()))))));
+    }
+
+    #[test]
+    fn test_set() {
+        let mut value: Value = Value::parse(br#"{"amount": 12345678901234567890.000001, "lines": [1, {"fee": 0.10}]}"#).unwrap();
+        let amount: Decimal = value.get(&Query::from("amount")).and_then(Value::as_decimal).unwrap();
+        assert_eq!(amount.to_string(), "12345678901234567890.000001");
+        assert_eq!(value.set(&Query::from("lines.[1].fee"), Value::from(u128::MAX)), Some(Value::Number("0.10".to_string())));
+        assert_eq!(value.set(&Query::from("lines.[0]"), Value::from("-1e-30".parse::<Decimal>().unwrap())), Some(Value::Number("1".to_string())));
+        assert_eq!(value.set(&Query::from("lines.[2]"), Value::Null), None);
+        assert_eq!(value.to_bytes(), br#"{"amount":12345678901234567890.000001,"lines":[-1e-30,{"fee":340282366920938463463374607431768211455}]}"#.to_vec());
+    }
+
+    #[test]
+    fn test_parse_with_limits() {
+        let limits: Limits = Limits{ max_array_length: 2, ..Limits::default() };
+        assert!(Value::parse_with_limits(b"[1, 2]", limits).is_some());
+        assert_eq!(Value::parse_with_limits(b"[1, 2, 3]", limits), None);
+        // nesting that would otherwise exhaust the stack is turned away
+        let deep: Vec<u8> = [vec!(b'['; 100_000), vec!(b']'; 100_000)].concat();
+        assert_eq!(Value::parse(&deep), None);
+        assert_eq!(Value::read(&mut JSONReader::new(&deep)), None);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        assert_eq!(Value::parse(JSON).unwrap().to_bytes(), JSON);
+        let escaped: &[u8] = br#" { "a\"b" : [ "line\nbreak\u0001", 1.50e3 , {} , [ ] ] } "#;
+        assert_eq!(Value::parse(escaped).unwrap().to_bytes(), br#"{"a\"b":["line\nbreak\u0001",1.50e3,{},[]]}"#.to_vec());
+    }
+}
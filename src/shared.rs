@@ -0,0 +1,145 @@
+//! A [Reader] over a [Bytes] buffer that hands results back as [Bytes].
+//!
+//! Extracted values are sub-slices of the original buffer: they share its allocation
+//! and keep it alive, so they can be forwarded or stored without copying.
+
+use bytes::Bytes;
+use crate::engine;
+use crate::query::Query;
+use crate::reader::{JSONRange, JSONType, JSONValue, Reader};
+use crate::typed::ValueError;
+
+pub struct BytesReader {
+    data: Bytes,
+    offset: usize,
+}
+
+impl BytesReader {
+    pub fn new(data: Bytes) -> BytesReader {
+        BytesReader{ data, offset: 0 }
+    }
+
+    /// The whole buffer being read
+    pub fn data(&self) -> &Bytes {
+        &self.data
+    }
+
+    pub fn into_inner(self) -> Bytes {
+        self.data
+    }
+
+    /// The bytes of a value read from this buffer, strings exclude their quotes.
+    /// Fails if the value was read from some other document.
+    pub fn slice(&self, value: &JSONValue) -> Result<Bytes, ValueError> {
+        value.bytes(&self.data)?;
+        Ok(self.data.slice(value.range.0..value.range.1))
+    }
+
+    /// Resolves [query] against the whole buffer, see [engine::select]
+    pub fn select(&self, query: &Query) -> Vec<(JSONType, Bytes)> {
+        engine::select(&self.data, query).iter()
+            .filter_map(|value| self.slice(value).ok().map(|bytes| (value.json_type, bytes)))
+            .collect()
+    }
+
+    /// The first value [query] selects
+    pub fn get(&self, query: &str) -> Option<Bytes> {
+        engine::select(&self.data, &Query::from(query)).first().and_then(|value| self.slice(value).ok())
+    }
+}
+
+impl From<Bytes> for BytesReader {
+    fn from(data: Bytes) -> BytesReader {
+        BytesReader::new(data)
+    }
+}
+
+impl Reader for BytesReader {
+
+    fn position(&mut self) -> usize {
+        match self.offset {
+            0 => 0,
+            _ => self.offset - 1
+        }
+    }
+
+    fn offset(&self) -> usize {
+        self.offset
+    }
+
+    fn next(&mut self) -> Option<u8> {
+        let next: u8 = *self.data.get(self.offset)?;
+        self.offset += 1;
+        Some(next)
+    }
+
+    fn prev(&mut self) -> Option<u8> {
+        if self.offset == 0 {
+            return None
+        }
+        self.offset -= 1;
+        self.data.get(self.offset).copied()
+    }
+
+    fn peek(&mut self) -> Option<u8> {
+        self.data.get(self.offset).copied()
+    }
+
+    fn last(&self) -> Option<u8> {
+        self.data.get(self.offset.checked_sub(1)?).copied()
+    }
+
+    fn current(&self) -> Option<u8> {
+        self.data.get(self.offset.saturating_sub(1)).copied()
+    }
+
+    fn seek(&mut self, offset: usize) {
+        self.offset = offset
+    }
+
+    fn print_at(&self, start: usize, end: usize) {
+        if let Some(bytes) = self.data.get(start..end) {
+            println!("printing: '{}'", String::from_utf8_lossy(bytes))
+        }
+    }
+
+    fn select(&mut self, range: JSONRange) -> Option<&[u8]> {
+        self.data.get(range.0..range.1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use crate::query::Query;
+    use crate::reader::{JSONType, JSONValue, Reader};
+    use crate::shared::BytesReader;
+    use crate::typed::ValueError;
+
+    const JSON: &[u8] = r#"{"name":"alex","hobbies":["cooking","guitar"],"nested":{"foo":"bar","herp":false}}"#.as_bytes();
+
+    #[test]
+    fn test_select_shares_buffer() {
+        let data: Bytes = Bytes::from(JSON.to_vec());
+        let reader: BytesReader = BytesReader::new(data.clone());
+        let nested: Bytes = reader.get("nested").unwrap();
+        assert_eq!(nested.as_ref(), br#"{"foo":"bar","herp":false}"#);
+        let start: usize = nested.as_ptr() as usize - data.as_ptr() as usize;
+        assert_eq!(start, 55);
+        assert_eq!(reader.select(&Query::from("hobbies | strings")), vec!(
+            (JSONType::STRING, Bytes::from_static(b"cooking")),
+            (JSONType::STRING, Bytes::from_static(b"guitar")),
+        ));
+        assert!(reader.get("missing").is_none());
+    }
+
+    #[test]
+    fn test_reader() {
+        let mut reader: BytesReader = BytesReader::from(Bytes::from_static(JSON));
+        reader.find_key(b"name").unwrap();
+        let value: JSONValue = reader.read_value().unwrap();
+        assert_eq!(reader.slice(&value), Ok(Bytes::from_static(b"alex")));
+        // a value from a longer document is out of bounds here
+        assert_eq!(BytesReader::from(Bytes::from_static(b"{}")).slice(&value), Err(ValueError::OutOfBounds));
+    }
+}
@@ -0,0 +1,273 @@
+//! Persists a [Tape] next to the file it indexes, as `<file>.gsjfidx`.
+//!
+//! The sidecar records the length and an FNV-1a checksum of the source it was built
+//! from. Loading it checks both against the current file and rebuilds the tape when
+//! they differ, so an edited source is never queried through a stale index.
+//!
+//! Layout, all integers little endian u64 unless noted:
+//! magic `GSJFIDX`, format version (u8), source length, source checksum, entry count,
+//! lookup count, then every entry (type (u8), start, end, has key (u8), key start,
+//! key end, next, children start, children end) and every lookup pair (slot, entry).
+
+use std::convert::TryFrom;
+use std::ffi::OsString;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use crate::limits::Limits;
+use crate::mmap::MappedFile;
+use crate::query::Query;
+use crate::reader::{JSONType, JSONValue};
+use crate::tape::{fnv1a, Entry, Tape};
+use crate::typed::ValueError;
+
+const MAGIC: &[u8] = b"GSJFIDX";
+const VERSION: u8 = 1;
+
+/// Where the sidecar of [source] lives, e.g. data.json.gsjfidx
+pub fn sidecar_path(source: &Path) -> PathBuf {
+    let mut path: OsString = source.as_os_str().to_owned();
+    path.push(".gsjfidx");
+    PathBuf::from(path)
+}
+
+fn type_tag(json_type: JSONType) -> u8 {
+    match json_type {
+        JSONType::ARRAY => 0,
+        JSONType::OBJECT => 1,
+        JSONType::NULL => 2,
+        JSONType::STRING => 3,
+        JSONType::BOOLEAN => 4,
+        JSONType::NUMBER => 5,
+    }
+}
+
+fn tag_type(tag: u8) -> Option<JSONType> {
+    match tag {
+        0 => Some(JSONType::ARRAY),
+        1 => Some(JSONType::OBJECT),
+        2 => Some(JSONType::NULL),
+        3 => Some(JSONType::STRING),
+        4 => Some(JSONType::BOOLEAN),
+        5 => Some(JSONType::NUMBER),
+        _ => None
+    }
+}
+
+fn put(out: &mut Vec<u8>, value: usize) {
+    out.extend_from_slice(&(value as u64).to_le_bytes())
+}
+
+/// Serializes [tape], which was built from [source]
+pub fn encode(tape: &Tape, source: &[u8]) -> Vec<u8> {
+    let mut out: Vec<u8> = Vec::with_capacity(64 + tape.entries.len() * 58 + tape.lookup.len() * 16);
+    out.extend_from_slice(MAGIC);
+    out.push(VERSION);
+    put(&mut out, source.len());
+    out.extend_from_slice(&fnv1a(source).to_le_bytes());
+    put(&mut out, tape.entries.len());
+    put(&mut out, tape.lookup.len());
+    for entry in &tape.entries {
+        out.push(type_tag(entry.value.json_type));
+        put(&mut out, entry.value.range.0);
+        put(&mut out, entry.value.range.1);
+        let (has_key, key): (u8, (usize, usize)) = match entry.key {
+            Some(key) => (1, key),
+            None => (0, (0, 0))
+        };
+        out.push(has_key);
+        put(&mut out, key.0);
+        put(&mut out, key.1);
+        put(&mut out, entry.next);
+        put(&mut out, entry.children.0);
+        put(&mut out, entry.children.1);
+    }
+    for (slot, idx) in &tape.lookup {
+        out.extend_from_slice(&slot.to_le_bytes());
+        put(&mut out, *idx);
+    }
+    out
+}
+
+/// Reads a sidecar back, None if it is malformed or was not built from [source]
+pub fn decode(bytes: &[u8], source: &[u8]) -> Option<Tape> {
+    let mut input: Input = Input{ bytes };
+    if input.take(MAGIC.len())? != MAGIC || input.byte()? != VERSION {
+        return None
+    }
+    if input.usize()? != source.len() || input.u64()? != fnv1a(source) {
+        return None // the source changed since the sidecar was written
+    }
+    let (entries, lookup): (usize, usize) = (input.usize()?, input.usize()?);
+    let mut tape: Tape = Tape{ entries: Vec::with_capacity(entries.min(bytes.len())), lookup: vec!() };
+    for _ in 0..entries {
+        let json_type: JSONType = tag_type(input.byte()?)?;
+        let range: (usize, usize) = (input.usize()?, input.usize()?);
+        let has_key: u8 = input.byte()?;
+        let key: (usize, usize) = (input.usize()?, input.usize()?);
+        let next: usize = input.usize()?;
+        let children: (usize, usize) = (input.usize()?, input.usize()?);
+        if range.0 > range.1 || range.1 > source.len() || key.1 > source.len() || next <= tape.entries.len() || next > entries || children.0 > children.1 || children.1 > lookup {
+            return None
+        }
+        tape.entries.push(Entry{
+            value: JSONValue::new(json_type, range),
+            key: if has_key == 1 { Some(key) } else { None },
+            next,
+            children,
+        });
+    }
+    tape.lookup.reserve(lookup.min(bytes.len()));
+    for _ in 0..lookup {
+        let slot: u64 = input.u64()?;
+        let idx: usize = input.usize()?;
+        if idx >= entries {
+            return None
+        }
+        tape.lookup.push((slot, idx));
+    }
+    match input.bytes.is_empty() {
+        true => Some(tape),
+        false => None
+    }
+}
+
+struct Input<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Input<'a> {
+    fn take(&mut self, count: usize) -> Option<&'a [u8]> {
+        if self.bytes.len() < count {
+            return None
+        }
+        let (taken, rest) = self.bytes.split_at(count);
+        self.bytes = rest;
+        Some(taken)
+    }
+
+    fn byte(&mut self) -> Option<u8> {
+        self.take(1).map(|taken| taken[0])
+    }
+
+    fn u64(&mut self) -> Option<u64> {
+        let mut buf: [u8; 8] = [0; 8];
+        buf.copy_from_slice(self.take(8)?);
+        Some(u64::from_le_bytes(buf))
+    }
+
+    fn usize(&mut self) -> Option<usize> {
+        usize::try_from(self.u64()?).ok()
+    }
+}
+
+/// A memory-mapped file queried through its persisted tape
+pub struct IndexedFile {
+    file: MappedFile,
+    tape: Tape,
+}
+
+impl IndexedFile {
+    /// Maps [path] and loads its sidecar, rebuilding the tape when the sidecar is missing,
+    /// malformed or stale. A rebuilt tape is written back; failing to write it only costs
+    /// the next run a rebuild, so that error is ignored. The file is trusted, no limits apply.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<IndexedFile> {
+        IndexedFile::open_with_limits(path, Limits::unlimited())
+    }
+
+    /// [IndexedFile::open], failing with [io::ErrorKind::InvalidData] when the tape has to be
+    /// rebuilt and the file goes past [limits]. A sidecar that is still current is loaded as is.
+    pub fn open_with_limits<P: AsRef<Path>>(path: P, limits: Limits) -> io::Result<IndexedFile> {
+        let file: MappedFile = MappedFile::open(path.as_ref())?;
+        let sidecar: PathBuf = sidecar_path(path.as_ref());
+        let loaded: Option<Tape> = fs::read(&sidecar).ok().and_then(|bytes| decode(&bytes, file.as_bytes()));
+        let tape: Tape = match loaded {
+            Some(tape) => tape,
+            None => {
+                let tape: Tape = Tape::build_with_limits(file.as_bytes(), limits)
+                    .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+                let _ = fs::write(&sidecar, encode(&tape, file.as_bytes()));
+                tape
+            }
+        };
+        Ok(IndexedFile{ file, tape })
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        self.file.as_bytes()
+    }
+
+    pub fn tape(&self) -> &Tape {
+        &self.tape
+    }
+
+    /// Resolves [query] through the tape, see [Tape::select]
+    pub fn select(&self, query: &Query) -> Vec<JSONValue> {
+        self.tape.select(self.file.as_bytes(), query)
+    }
+
+    /// The bytes of a value selected from this file, strings exclude their quotes.
+    /// Fails if the value was read from some other document.
+    pub fn slice(&self, value: &JSONValue) -> Result<&[u8], ValueError> {
+        self.file.slice(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use crate::limits::Limits;
+    use crate::query::Query;
+    use crate::sidecar::{decode, encode, sidecar_path, IndexedFile};
+    use crate::tape::Tape;
+
+    const JSON: &[u8] = r#"{"name":"alex","hobbies":["cooking","guitar"],"nested":{"foo":"bar","herp":false}}"#.as_bytes();
+
+    #[test]
+    fn test_round_trip() {
+        let tape: Tape = Tape::build(JSON).unwrap();
+        let bytes: Vec<u8> = encode(&tape, JSON);
+        assert_eq!(decode(&bytes, JSON), Some(tape));
+        assert_eq!(decode(&bytes, br#"{"name":"alex"}"#), None);
+        assert_eq!(decode(&bytes[..bytes.len() - 1], JSON), None);
+        let mut changed: Vec<u8> = JSON.to_vec();
+        changed[10] = b'X';
+        assert_eq!(decode(&bytes, &changed), None);
+    }
+
+    #[test]
+    fn test_indexed_file() {
+        let path: PathBuf = std::env::temp_dir().join(format!("gsjf-{}-sidecar.json", std::process::id()));
+        let sidecar: PathBuf = sidecar_path(&path);
+        std::fs::write(&path, JSON).unwrap();
+
+        let file: IndexedFile = IndexedFile::open(&path).unwrap();
+        assert!(sidecar.exists());
+        let herp = file.select(&Query::from("nested.herp"));
+        assert_eq!(file.slice(&herp[0]), Ok(b"false".as_ref()));
+        drop(file);
+
+        // the sidecar is reused, then replaced once the source changes
+        let file: IndexedFile = IndexedFile::open(&path).unwrap();
+        assert_eq!(file.slice(&file.select(&Query::from("hobbies.[1]"))[0]), Ok(b"guitar".as_ref()));
+        drop(file);
+        std::fs::write(&path, br#"{"hobbies": ["none"]}"#).unwrap();
+        let file: IndexedFile = IndexedFile::open(&path).unwrap();
+        assert_eq!(file.slice(&file.select(&Query::from("hobbies.[0]"))[0]), Ok(b"none".as_ref()));
+        assert!(file.select(&Query::from("hobbies.[1]")).is_empty());
+        drop(file);
+
+        // limits only apply when the tape is rebuilt
+        let limits: Limits = Limits{ max_document_size: 8, ..Limits::default() };
+        assert!(IndexedFile::open_with_limits(&path, limits).is_ok());
+        std::fs::remove_file(&sidecar).unwrap();
+        assert_eq!(IndexedFile::open_with_limits(&path, limits).err().map(|error| error.kind()), Some(std::io::ErrorKind::InvalidData));
+        assert!(!sidecar.exists());
+        assert!(IndexedFile::open(&path).is_ok());
+
+        std::fs::write(&path, b"[1, ").unwrap();
+        assert!(IndexedFile::open(&path).is_err());
+        std::fs::remove_file(&path).unwrap();
+        std::fs::remove_file(&sidecar).unwrap();
+    }
+}
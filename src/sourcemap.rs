@@ -0,0 +1,119 @@
+//! Maps byte offsets back to lines and columns of the original text, so a query path
+//! can be reported the way an editor shows it, e.g. for pointing a linter at a key.
+//!
+//! Lines and columns start at 1. Columns count characters rather than bytes, so
+//! multi-byte UTF-8 characters take one column; a \r before a line break counts as
+//! the last column of its line.
+
+use crate::engine;
+use crate::query::Query;
+use crate::reader::{JSONRange, JSONType, JSONValue};
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub struct Location {
+    pub line: usize,
+    pub column: usize,
+}
+
+/// From [start] up to, not including, [end]
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Span {
+    pub start: Location,
+    pub end: Location,
+}
+
+/// Where a query's path leads, strings and keys including their quotes
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Located {
+    /// The key of the last step, None for the root and for array elements
+    pub key: Option<Span>,
+    pub value: Span,
+    pub json_type: JSONType,
+}
+
+/// The offsets where lines start, for answering many lookups into one document
+pub struct SourceMap<'a> {
+    json: &'a [u8],
+    lines: Vec<usize>,
+}
+
+impl<'a> SourceMap<'a> {
+    pub fn new(json: &'a [u8]) -> SourceMap<'a> {
+        let mut lines: Vec<usize> = vec!(0);
+        lines.extend(json.iter().enumerate().filter(|(_, byte)| **byte == b'\n').map(|(idx, _)| idx + 1));
+        SourceMap{ json, lines }
+    }
+
+    /// The location of the byte at [offset], offsets past the end map to just after the last byte
+    pub fn location(&self, offset: usize) -> Location {
+        let offset: usize = offset.min(self.json.len());
+        let line: usize = self.lines.partition_point(|start| *start <= offset) - 1;
+        let characters: usize = self.json[self.lines[line]..offset].iter()
+            .filter(|byte| **byte & 0xC0 != 0x80) // UTF-8 continuation bytes share their character's column
+            .count();
+        Location{ line: line + 1, column: characters + 1 }
+    }
+
+    pub fn span(&self, range: JSONRange) -> Span {
+        Span{ start: self.location(range.0), end: self.location(range.1) }
+    }
+
+    /// Follows the query's path like [crate::engine::select], ignoring its filters.
+    /// None if the path leads nowhere.
+    pub fn locate(&self, query: &Query) -> Option<Located> {
+        let (key, value): (Option<JSONRange>, JSONValue) = engine::resolve(self.json, query)?;
+        Some(Located{
+            key: key.map(|(start, end)| self.span((start - 1, end + 1))),
+            value: self.span(value.extent()),
+            json_type: value.json_type,
+        })
+    }
+}
+
+/// Where the query's path leads in [json], see [SourceMap::locate]
+pub fn locate(json: &[u8], query: &Query) -> Option<Located> {
+    SourceMap::new(json).locate(query)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::query::Query;
+    use crate::reader::JSONType;
+    use crate::sourcemap::{locate, Located, Location, SourceMap, Span};
+
+    const CONFIG: &[u8] = "{\n  \"name\": \"zoë\",\r\n  \"ports\": [\n    80,\n    { \"tls\": true }\n  ]\n}\n".as_bytes();
+
+    fn span(from: (usize, usize), to: (usize, usize)) -> Span {
+        Span{ start: Location{ line: from.0, column: from.1 }, end: Location{ line: to.0, column: to.1 } }
+    }
+
+    #[test]
+    fn test_location() {
+        let map: SourceMap = SourceMap::new(CONFIG);
+        assert_eq!(map.location(0), Location{ line: 1, column: 1 });
+        assert_eq!(map.location(1), Location{ line: 1, column: 2 });
+        assert_eq!(map.location(2), Location{ line: 2, column: 1 });
+        assert_eq!(map.location(CONFIG.len()), Location{ line: 8, column: 1 });
+        assert_eq!(map.location(usize::MAX), Location{ line: 8, column: 1 });
+    }
+
+    #[test]
+    fn test_locate() {
+        assert_eq!(locate(CONFIG, &Query::from("name")), Some(Located{
+            key: Some(span((2, 3), (2, 9))),
+            value: span((2, 11), (2, 16)), // ë is a single column
+            json_type: JSONType::STRING,
+        }));
+        assert_eq!(locate(CONFIG, &Query::from("ports.[1].tls")), Some(Located{
+            key: Some(span((5, 7), (5, 12))),
+            value: span((5, 14), (5, 18)),
+            json_type: JSONType::BOOLEAN,
+        }));
+        let ports: Located = locate(CONFIG, &Query::from("ports")).unwrap();
+        assert_eq!(ports.value, span((3, 12), (6, 4)));
+        assert_eq!(locate(CONFIG, &Query::from("ports.[0]")).map(|port| (port.key, port.value)), Some((None, span((4, 5), (4, 7)))));
+        assert_eq!(locate(CONFIG, &Query::from("")).map(|root| root.value), Some(span((1, 1), (7, 2))));
+        assert_eq!(locate(CONFIG, &Query::from("ports.[2]")), None);
+        assert_eq!(locate(CONFIG, &Query::from("name.first")), None);
+    }
+}
@@ -0,0 +1,277 @@
+//! A [Reader] pulling from any [io::Read] through a refillable buffer.
+//!
+//! Offsets and ranges are absolute positions in the stream, like they are for a
+//! [crate::reader::JSONReader]. Whatever has been [Reader::release]d is dropped from
+//! the buffer on its next refill, so memory is bounded by the largest value being
+//! looked at rather than by the size of the document.
+//!
+//! Validating through [crate::validation::Validator] enforces [crate::limits::Limits::default] like on
+//! any reader, and its document size stops at 256 MiB. Since memory is bounded by the
+//! buffer anyway, validate larger exports with `check_with_limits(Limits::unlimited())`.
+
+use std::io::{self, Read};
+use crate::iter;
+use crate::query::Query;
+use crate::reader::{JSONRange, JSONType, JSONValue, Reader};
+
+const CHUNK_SIZE: usize = 64 * 1024;
+
+pub struct StreamReader<R: Read> {
+    source: R,
+    buffer: Vec<u8>,
+    /// The stream offset of buffer[0]
+    base: usize,
+    offset: usize,
+    /// Nothing before this offset has to be kept around
+    released: usize,
+    chunk_size: usize,
+    exhausted: bool,
+    error: Option<io::Error>,
+}
+
+impl<R: Read> StreamReader<R> {
+    pub fn new(source: R) -> StreamReader<R> {
+        StreamReader::with_chunk_size(source, CHUNK_SIZE)
+    }
+
+    /// [chunk_size] is how many bytes are requested from the source per refill
+    pub fn with_chunk_size(source: R, chunk_size: usize) -> StreamReader<R> {
+        StreamReader{
+            source,
+            buffer: vec!(),
+            base: 0,
+            offset: 0,
+            released: 0,
+            chunk_size: chunk_size.max(1),
+            exhausted: false,
+            error: None,
+        }
+    }
+
+    /// The error that stopped reading from the source, if any. The reader treats it as
+    /// the end of the stream, so check this after a read comes up short.
+    pub fn error(&self) -> Option<&io::Error> {
+        self.error.as_ref()
+    }
+
+    /// How many bytes are currently held in memory
+    pub fn buffered(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// Makes sure the byte at [offset] is buffered, false if the stream ends before it
+    fn fill(&mut self, offset: usize) -> bool {
+        if offset < self.base {
+            return false // already released
+        }
+        while offset >= self.base + self.buffer.len() {
+            if self.exhausted {
+                return false
+            }
+            self.compact();
+            let len: usize = self.buffer.len();
+            self.buffer.resize(len + self.chunk_size, 0);
+            match self.source.read(&mut self.buffer[len..]) {
+                Ok(0) => {
+                    self.buffer.truncate(len);
+                    self.exhausted = true;
+                },
+                Ok(read) => self.buffer.truncate(len + read),
+                Err(error) => {
+                    self.buffer.truncate(len);
+                    if error.kind() != io::ErrorKind::Interrupted {
+                        self.error = Some(error);
+                        self.exhausted = true;
+                    }
+                }
+            }
+        }
+        true
+    }
+
+    /// Drops released bytes from the buffer, always keeping the current token
+    fn compact(&mut self) {
+        let keep_from: usize = self.released.min(self.offset.saturating_sub(1));
+        if keep_from > self.base {
+            let discard: usize = (keep_from - self.base).min(self.buffer.len());
+            self.buffer.drain(..discard);
+            self.base += discard;
+        }
+    }
+
+    fn window(&self, range: JSONRange) -> Option<&[u8]> {
+        if range.0 < self.base || range.1 < range.0 {
+            return None
+        }
+        self.buffer.get(range.0 - self.base..range.1 - self.base)
+    }
+
+    fn copy(&mut self, value: JSONValue) -> Option<(JSONType, Vec<u8>)> {
+        self.window(value.range).map(|bytes| (value.json_type, bytes.to_vec()))
+    }
+
+    /// Resolves [query] from the reader's current position, copying out the selected
+    /// values since the stream moves on past them. Strings exclude their quotes.
+    pub fn query(&mut self, query: &Query) -> Vec<(JSONType, Vec<u8>)> {
+        for selector in query.components.iter().filter(|selector| !selector.path.is_empty()) {
+            let found: bool = match selector.index() {
+                Some(index) => iter::skip_elements(self, index),
+                None => self.find_key(selector.path).is_some()
+            };
+            if !found {
+                return vec!()
+            }
+        }
+        self.skip_past_whitespace();
+        if !query.filters.is_empty() && self.peek() == Some(b'[') {
+            let mut selected: Vec<(JSONType, Vec<u8>)> = vec!();
+            let mut started: bool = false;
+            loop {
+                let position: usize = self.position();
+                self.release(position);
+                match iter::next_element(self, &mut started) {
+                    None => return selected,
+                    Some(element) if query.keeps(element.json_type) => selected.extend(self.copy(element)),
+                    Some(_) => {}
+                }
+            }
+        }
+        let position: usize = self.position();
+        self.release(position);
+        match self.read_value() {
+            Some(value) if query.keeps(value.json_type) => self.copy(value).into_iter().collect(),
+            _ => vec!()
+        }
+    }
+}
+
+impl<R: Read> Reader for StreamReader<R> {
+
+    fn position(&mut self) -> usize {
+        match self.offset {
+            0 => 0,
+            _ => self.offset - 1
+        }
+    }
+
+    fn offset(&self) -> usize {
+        self.offset
+    }
+
+    fn next(&mut self) -> Option<u8> {
+        if !self.fill(self.offset) {
+            return None
+        }
+        let next: u8 = self.buffer[self.offset - self.base];
+        self.offset += 1;
+        Some(next)
+    }
+
+    fn prev(&mut self) -> Option<u8> {
+        if self.offset == 0 || self.offset - 1 < self.base {
+            return None
+        }
+        self.offset -= 1;
+        self.buffer.get(self.offset - self.base).copied()
+    }
+
+    fn peek(&mut self) -> Option<u8> {
+        if !self.fill(self.offset) {
+            return None
+        }
+        Some(self.buffer[self.offset - self.base])
+    }
+
+    fn last(&self) -> Option<u8> {
+        let last: usize = self.offset.checked_sub(1)?;
+        self.buffer.get(last.checked_sub(self.base)?).copied()
+    }
+
+    fn current(&self) -> Option<u8> {
+        let current: usize = self.offset.saturating_sub(1);
+        self.buffer.get(current.checked_sub(self.base)?).copied()
+    }
+
+    fn seek(&mut self, offset: usize) {
+        self.offset = offset
+    }
+
+    fn print_at(&self, start: usize, end: usize) {
+        if let Some(bytes) = self.window((start, end)) {
+            println!("printing: '{}'", String::from_utf8_lossy(bytes))
+        }
+    }
+
+    fn select(&mut self, range: JSONRange) -> Option<&[u8]> {
+        self.window(range)
+    }
+
+    fn release(&mut self, offset: usize) {
+        self.released = offset
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::limits::{Limit, Limits};
+    use crate::parser::ErrorKind;
+    use crate::query::Query;
+    use crate::reader::{JSONType, Reader};
+    use crate::stream::StreamReader;
+    use crate::validation::Validator;
+
+    /// An object with [members] string members, about 30 bytes each
+    fn document(members: usize) -> Vec<u8> {
+        let mut json: Vec<u8> = b"{".to_vec();
+        for idx in 0..members {
+            json.extend_from_slice(format!("\"key{}\": \"value number {}\",", idx, idx).as_bytes());
+        }
+        json.extend_from_slice(br#""last": {"list": [1, "two", null, "four"]}}"#);
+        json
+    }
+
+    #[test]
+    fn test_query() {
+        let json: Vec<u8> = document(10);
+        let mut reader = StreamReader::with_chunk_size(json.as_slice(), 7);
+        assert_eq!(reader.query(&Query::from("key3")), vec!((JSONType::STRING, b"value number 3".to_vec())));
+        let mut reader = StreamReader::with_chunk_size(json.as_slice(), 7);
+        assert_eq!(reader.query(&Query::from("last.list.[1]")), vec!((JSONType::STRING, b"two".to_vec())));
+        let mut reader = StreamReader::with_chunk_size(json.as_slice(), 7);
+        assert_eq!(reader.query(&Query::from("last.list | strings")), vec!(
+            (JSONType::STRING, b"two".to_vec()),
+            (JSONType::STRING, b"four".to_vec()),
+        ));
+        let mut reader = StreamReader::with_chunk_size(json.as_slice(), 7);
+        assert!(reader.query(&Query::from("missing")).is_empty());
+    }
+
+    #[test]
+    fn test_memory_is_bounded() {
+        let json: Vec<u8> = document(10_000);
+        let mut reader = StreamReader::with_chunk_size(json.as_slice(), 64);
+        assert_eq!(reader.query(&Query::from("last.list.[3]")), vec!((JSONType::STRING, b"four".to_vec())));
+        assert!(reader.buffered() < 256, "buffered {} bytes", reader.buffered());
+
+        let mut reader = StreamReader::with_chunk_size(json.as_slice(), 64);
+        assert!(reader.validate());
+        assert!(reader.buffered() < 256, "buffered {} bytes", reader.buffered());
+
+        // past the document size limit, which only unlimited validation gets through
+        let limits: Limits = Limits{ max_document_size: json.len() / 2, ..Limits::default() };
+        let mut reader = StreamReader::with_chunk_size(json.as_slice(), 64);
+        assert_eq!(reader.check_with_limits(limits).unwrap_err().kind, ErrorKind::LimitExceeded(Limit::DocumentSize));
+        let mut reader = StreamReader::with_chunk_size(json.as_slice(), 64);
+        assert_eq!(reader.check_with_limits(Limits::unlimited()), Ok(()));
+        assert!(reader.buffered() < 256, "buffered {} bytes", reader.buffered());
+    }
+
+    #[test]
+    fn test_validate() {
+        let mut reader = StreamReader::with_chunk_size(br#"{"a": [1, 2}"#.as_ref(), 3);
+        assert!(!reader.validate());
+        let mut reader = StreamReader::with_chunk_size(br#" [1, {"b": "c"}] "#.as_ref(), 3);
+        assert!(reader.validate());
+        assert_eq!(reader.next(), None);
+    }
+}
@@ -0,0 +1,259 @@
+//! Decoding of JSON string contents, i.e. the bytes between the quotes that
+//! [crate::reader::Reader::read_string] returns the range of.
+
+use std::borrow::Cow;
+use std::fmt;
+use unicode_normalization::UnicodeNormalization;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum StringError {
+    /// A backslash followed by something other than a valid escape, at the given offset
+    InvalidEscape(usize),
+    /// A \u escape that is not valid hex or leaves a surrogate unpaired, at the given offset
+    InvalidUnicode(usize),
+    /// Bytes that are not valid UTF-8, at the given offset
+    InvalidUtf8(usize),
+}
+
+impl fmt::Display for StringError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StringError::InvalidEscape(offset) => write!(f, "invalid escape sequence at offset {}", offset),
+            StringError::InvalidUnicode(offset) => write!(f, "invalid unicode escape at offset {}", offset),
+            StringError::InvalidUtf8(offset) => write!(f, "invalid UTF-8 at offset {}", offset),
+        }
+    }
+}
+
+impl std::error::Error for StringError {}
+
+/// Decodes raw string contents, borrowing them when there are no escapes to resolve
+pub fn decode(raw: &[u8]) -> Result<Cow<'_, str>, StringError> {
+    if !raw.contains(&b'\\') {
+        return std::str::from_utf8(raw)
+            .map(Cow::Borrowed)
+            .map_err(|error| StringError::InvalidUtf8(error.valid_up_to()))
+    }
+    Chars::new(raw).collect::<Result<String, StringError>>().map(Cow::Owned)
+}
+
+/// Iterates the characters of raw string contents, resolving escape sequences.
+/// Iteration stops after the first error.
+pub struct Chars<'a> {
+    raw: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Chars<'a> {
+    pub fn new(raw: &'a [u8]) -> Chars<'a> {
+        Chars{ raw, offset: 0 }
+    }
+
+    fn fail(&mut self, error: StringError) -> Option<Result<char, StringError>> {
+        self.offset = self.raw.len();
+        Some(Err(error))
+    }
+
+    /// Reads the four hex digits of a \u escape starting at [start]
+    fn hex(&self, start: usize) -> Option<u32> {
+        let digits: &[u8] = self.raw.get(start..start + 4)?;
+        let digits: &str = std::str::from_utf8(digits).ok()?;
+        if !digits.bytes().all(|digit| digit.is_ascii_hexdigit()) {
+            return None
+        }
+        u32::from_str_radix(digits, 16).ok()
+    }
+
+    /// Assumes [offset] is just past the "\u"
+    fn unicode(&mut self) -> Option<Result<char, StringError>> {
+        let start: usize = self.offset - 2;
+        let high: u32 = match self.hex(self.offset) {
+            None => return self.fail(StringError::InvalidUnicode(start)),
+            Some(high) => high
+        };
+        self.offset += 4;
+        let code: u32 = match high {
+            0xD800..=0xDBFF => {
+                if self.raw.get(self.offset..self.offset + 2) != Some(b"\\u") {
+                    return self.fail(StringError::InvalidUnicode(start))
+                }
+                match self.hex(self.offset + 2) {
+                    Some(low @ 0xDC00..=0xDFFF) => {
+                        self.offset += 6;
+                        0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00)
+                    },
+                    _ => return self.fail(StringError::InvalidUnicode(start))
+                }
+            },
+            0xDC00..=0xDFFF => return self.fail(StringError::InvalidUnicode(start)),
+            _ => high
+        };
+        match std::char::from_u32(code) {
+            None => self.fail(StringError::InvalidUnicode(start)),
+            Some(decoded) => Some(Ok(decoded))
+        }
+    }
+}
+
+impl Iterator for Chars<'_> {
+    type Item = Result<char, StringError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let start: usize = self.offset;
+        let token: u8 = *self.raw.get(start)?;
+        if token == b'\\' {
+            self.offset += 2;
+            let decoded: char = match self.raw.get(start + 1) {
+                Some(b'"') => '"',
+                Some(b'\\') => '\\',
+                Some(b'/') => '/',
+                Some(b'b') => '\u{8}',
+                Some(b'f') => '\u{c}',
+                Some(b'n') => '\n',
+                Some(b'r') => '\r',
+                Some(b't') => '\t',
+                Some(b'u') => return self.unicode(),
+                _ => return self.fail(StringError::InvalidEscape(start))
+            };
+            return Some(Ok(decoded))
+        }
+        let width: usize = match token {
+            0x00..=0x7F => 1,
+            0xC0..=0xDF => 2,
+            0xE0..=0xEF => 3,
+            0xF0..=0xF7 => 4,
+            _ => return self.fail(StringError::InvalidUtf8(start))
+        };
+        let decoded: Option<char> = self.raw.get(start..start + width)
+            .and_then(|bytes| std::str::from_utf8(bytes).ok())
+            .and_then(|text| text.chars().next());
+        match decoded {
+            None => self.fail(StringError::InvalidUtf8(start)),
+            Some(decoded) => {
+                self.offset += width;
+                Some(Ok(decoded))
+            }
+        }
+    }
+}
+
+/// Whether raw string contents decode to exactly [expected], without decoding all of them
+fn decodes_to(raw: &[u8], expected: &str) -> bool {
+    let mut decoded: Chars = Chars::new(raw);
+    let mut expected = expected.chars();
+    loop {
+        match (decoded.next(), expected.next()) {
+            (None, None) => return true,
+            (Some(Ok(found)), Some(wanted)) if found == wanted => continue,
+            _ => return false
+        }
+    }
+}
+
+/// How object keys in the document are compared against the key being looked up
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum KeyMatch {
+    /// Raw byte equality, escapes are compared as written
+    #[default]
+    Exact,
+    /// Escapes are resolved, so `\u0069d` matches `id`
+    Decoded,
+    /// Escapes are resolved and both keys are brought to Unicode normalisation form C,
+    /// so a precomposed `é` matches `e` followed by a combining acute accent
+    Normalized,
+    /// Like [KeyMatch::Normalized], also comparing characters by their lowercase forms
+    CaseInsensitive,
+}
+
+impl KeyMatch {
+    /// Whether the raw key contents from the document match [key]
+    pub fn matches(&self, raw: &[u8], key: &[u8]) -> bool {
+        if *self == KeyMatch::Exact {
+            return raw == key
+        }
+        let key: &str = match std::str::from_utf8(key) {
+            Err(_) => return false,
+            Ok(key) => key
+        };
+        if *self == KeyMatch::Decoded {
+            return decodes_to(raw, key)
+        }
+        let found: Cow<str> = match decode(raw) {
+            Err(_) => return false,
+            Ok(found) => found
+        };
+        match self {
+            KeyMatch::CaseInsensitive => found.chars().flat_map(char::to_lowercase).nfc()
+                .eq(key.chars().flat_map(char::to_lowercase).nfc()),
+            _ => found.nfc().eq(key.nfc())
+        }
+    }
+
+    /// The matching that resolves escapes, [KeyMatch::Exact] becomes [KeyMatch::Decoded]
+    pub(crate) fn decoded(self) -> KeyMatch {
+        match self {
+            KeyMatch::Exact => KeyMatch::Decoded,
+            matching => matching
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+    use crate::string::{decode as decode_cow, Chars, KeyMatch, StringError};
+
+    fn decode(raw: &[u8]) -> Result<String, StringError> {
+        Chars::new(raw).collect()
+    }
+
+    #[test]
+    fn test_chars() {
+        assert_eq!(decode(br#"a\"b\\c\/\n\t"#), Ok("a\"b\\c/\n\t".to_string()));
+        assert_eq!(decode("été".as_bytes()), Ok("été".to_string()));
+        assert_eq!(decode(br#"\ud83d\ude00"#), Ok("😀".to_string()));
+        assert_eq!(decode("😀".as_bytes()), Ok("😀".to_string()));
+    }
+
+    #[test]
+    fn test_chars_invalid() {
+        assert_eq!(decode(br#"ab\x"#), Err(StringError::InvalidEscape(2)));
+        assert_eq!(decode(br#"\ud83d"#), Err(StringError::InvalidUnicode(0)));
+        assert_eq!(decode(br#"x\ude00"#), Err(StringError::InvalidUnicode(1)));
+        assert_eq!(decode(br#"\u12g4"#), Err(StringError::InvalidUnicode(0)));
+        assert_eq!(decode(b"a\xffb"), Err(StringError::InvalidUtf8(1)));
+    }
+
+    #[test]
+    fn test_decode() {
+        assert_eq!(decode_cow(b"plain"), Ok(Cow::Borrowed("plain")));
+        assert!(matches!(decode_cow(br#"say \"hi\"\n"#), Ok(Cow::Owned(ref decoded)) if decoded == "say \"hi\"\n"));
+        assert_eq!(decode_cow(br#"\ud83d\ude00!"#).unwrap(), "😀!");
+        assert_eq!(decode_cow(b"ab\xc3"), Err(StringError::InvalidUtf8(2)));
+        assert_eq!(decode_cow(br#"\udc00"#), Err(StringError::InvalidUnicode(0)));
+    }
+
+    #[test]
+    fn test_key_match() {
+        assert!(KeyMatch::Exact.matches(b"userId", b"userId"));
+        assert!(!KeyMatch::Exact.matches(br#"\u0075serId"#, b"userId"));
+        assert!(KeyMatch::Decoded.matches(br#"\u0075serId"#, b"userId"));
+        assert!(!KeyMatch::Decoded.matches(b"UserID", b"userId"));
+        assert!(KeyMatch::CaseInsensitive.matches(b"UserID", b"userId"));
+        assert!(KeyMatch::CaseInsensitive.matches(br#"\u0055serID"#, b"userid"));
+        assert!(!KeyMatch::CaseInsensitive.matches(b"userIds", b"userId"));
+    }
+
+    #[test]
+    fn test_key_match_normalized() {
+        let composed: &[u8] = "caf\u{e9}".as_bytes();
+        let decomposed: &[u8] = "cafe\u{301}".as_bytes();
+        assert!(!KeyMatch::Decoded.matches(decomposed, composed));
+        assert!(KeyMatch::Normalized.matches(decomposed, composed));
+        assert!(KeyMatch::Normalized.matches(br#"cafe\u0301"#, composed));
+        assert!(KeyMatch::Normalized.matches(composed, decomposed));
+        assert!(!KeyMatch::Normalized.matches(b"CAFE\xCC\x81", composed));
+        assert!(KeyMatch::CaseInsensitive.matches("CAF\u{c9}".as_bytes(), decomposed));
+        assert!(!KeyMatch::Normalized.matches(b"cafe", composed));
+    }
+}
@@ -0,0 +1,429 @@
+//! A simdjson style stage-1 pass: the offsets of every structural character.
+//!
+//! The document is classified 64 bytes at a time into bitmasks of quotes, backslashes
+//! and structural characters, with SSE2 or AVX2 on x86_64 and plain loops elsewhere.
+//! Escaped quotes are masked out, a prefix xor of the remaining quotes marks what is
+//! inside strings, and the index keeps `{ } [ ] : ,` outside strings plus the opening
+//! quote of every string. Lookups then hop between structurals instead of visiting
+//! every byte, and skip a nested container by counting brackets in the index alone.
+
+use std::convert::TryInto;
+use crate::reader::{JSONReader, JSONType, JSONValue, Reader};
+
+const BLOCK: usize = 64;
+const EVEN_BITS: u64 = 0x5555_5555_5555_5555;
+
+/// Bitmasks of one block, bit i describes byte i
+#[derive(Debug, PartialEq, Default)]
+struct Block {
+    quote: u64,
+    backslash: u64,
+    structural: u64,
+}
+
+/// The implementation classifying blocks, picked once per build
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub(crate) enum Kernel {
+    /// Only picked off x86_64, but always built so the other kernels can be checked against it
+    #[cfg_attr(target_arch = "x86_64", allow(dead_code))]
+    Scalar,
+    #[cfg(target_arch = "x86_64")]
+    Sse2,
+    #[cfg(target_arch = "x86_64")]
+    Avx2,
+}
+
+impl Kernel {
+    /// The fastest kernel the running CPU supports
+    pub(crate) fn detect() -> Kernel {
+        #[cfg(target_arch = "x86_64")]
+        {
+            if is_x86_feature_detected!("avx2") {
+                return Kernel::Avx2
+            }
+            Kernel::Sse2
+        }
+        #[cfg(not(target_arch = "x86_64"))]
+        Kernel::Scalar
+    }
+
+    fn classify(self, block: &[u8; BLOCK]) -> Block {
+        match self {
+            Kernel::Scalar => classify_scalar(block),
+            #[cfg(target_arch = "x86_64")]
+            Kernel::Sse2 => unsafe { x86::classify_sse2(block) },
+            #[cfg(target_arch = "x86_64")]
+            Kernel::Avx2 => unsafe { x86::classify_avx2(block) },
+        }
+    }
+}
+
+fn classify_scalar(block: &[u8; BLOCK]) -> Block {
+    let mut bits: Block = Block::default();
+    for (idx, byte) in block.iter().enumerate() {
+        let bit: u64 = 1 << idx;
+        match byte {
+            b'"' => bits.quote |= bit,
+            b'\\' => bits.backslash |= bit,
+            b'{' | b'}' | b'[' | b']' | b':' | b',' => bits.structural |= bit,
+            _ => {}
+        }
+    }
+    bits
+}
+
+#[cfg(target_arch = "x86_64")]
+mod x86 {
+    use std::arch::x86_64::*;
+    use super::{Block, BLOCK};
+
+    macro_rules! mask {
+        ($cmpeq:ident, $movemask:ident, $set1:ident, $chunk:expr, $byte:expr) => {
+            $movemask($cmpeq($chunk, $set1($byte as i8)))
+        };
+    }
+
+    /// # Safety
+    /// SSE2 is part of the x86_64 baseline, this is only unsafe for the raw loads
+    pub(super) unsafe fn classify_sse2(block: &[u8; BLOCK]) -> Block {
+        let mut bits: Block = Block::default();
+        for lane in 0..BLOCK / 16 {
+            let chunk: __m128i = _mm_loadu_si128(block.as_ptr().add(lane * 16) as *const __m128i);
+            let quote: i32 = mask!(_mm_cmpeq_epi8, _mm_movemask_epi8, _mm_set1_epi8, chunk, b'"');
+            let backslash: i32 = mask!(_mm_cmpeq_epi8, _mm_movemask_epi8, _mm_set1_epi8, chunk, b'\\');
+            let structural: i32 = mask!(_mm_cmpeq_epi8, _mm_movemask_epi8, _mm_set1_epi8, chunk, b'{')
+                | mask!(_mm_cmpeq_epi8, _mm_movemask_epi8, _mm_set1_epi8, chunk, b'}')
+                | mask!(_mm_cmpeq_epi8, _mm_movemask_epi8, _mm_set1_epi8, chunk, b'[')
+                | mask!(_mm_cmpeq_epi8, _mm_movemask_epi8, _mm_set1_epi8, chunk, b']')
+                | mask!(_mm_cmpeq_epi8, _mm_movemask_epi8, _mm_set1_epi8, chunk, b':')
+                | mask!(_mm_cmpeq_epi8, _mm_movemask_epi8, _mm_set1_epi8, chunk, b',');
+            bits.quote |= (quote as u16 as u64) << (lane * 16);
+            bits.backslash |= (backslash as u16 as u64) << (lane * 16);
+            bits.structural |= (structural as u16 as u64) << (lane * 16);
+        }
+        bits
+    }
+
+    /// # Safety
+    /// The CPU must support AVX2, see [super::Kernel::detect]
+    #[target_feature(enable = "avx2")]
+    pub(super) unsafe fn classify_avx2(block: &[u8; BLOCK]) -> Block {
+        let mut bits: Block = Block::default();
+        for lane in 0..BLOCK / 32 {
+            let chunk: __m256i = _mm256_loadu_si256(block.as_ptr().add(lane * 32) as *const __m256i);
+            let quote: i32 = mask!(_mm256_cmpeq_epi8, _mm256_movemask_epi8, _mm256_set1_epi8, chunk, b'"');
+            let backslash: i32 = mask!(_mm256_cmpeq_epi8, _mm256_movemask_epi8, _mm256_set1_epi8, chunk, b'\\');
+            let structural: i32 = mask!(_mm256_cmpeq_epi8, _mm256_movemask_epi8, _mm256_set1_epi8, chunk, b'{')
+                | mask!(_mm256_cmpeq_epi8, _mm256_movemask_epi8, _mm256_set1_epi8, chunk, b'}')
+                | mask!(_mm256_cmpeq_epi8, _mm256_movemask_epi8, _mm256_set1_epi8, chunk, b'[')
+                | mask!(_mm256_cmpeq_epi8, _mm256_movemask_epi8, _mm256_set1_epi8, chunk, b']')
+                | mask!(_mm256_cmpeq_epi8, _mm256_movemask_epi8, _mm256_set1_epi8, chunk, b':')
+                | mask!(_mm256_cmpeq_epi8, _mm256_movemask_epi8, _mm256_set1_epi8, chunk, b',');
+            bits.quote |= (quote as u32 as u64) << (lane * 32);
+            bits.backslash |= (backslash as u32 as u64) << (lane * 32);
+            bits.structural |= (structural as u32 as u64) << (lane * 32);
+        }
+        bits
+    }
+}
+
+/// The bits of characters escaped by a backslash. Runs of backslashes escape each
+/// other in pairs, so only the byte after an odd length run is escaped. [carry] holds
+/// whether the first byte of the next block is escaped.
+fn find_escaped(backslash: u64, carry: &mut u64) -> u64 {
+    let backslash: u64 = backslash & !*carry;
+    let follows_escape: u64 = backslash << 1 | *carry;
+    let odd_starts: u64 = backslash & !EVEN_BITS & !follows_escape;
+    let (even_starts, overflow) = odd_starts.overflowing_add(backslash);
+    *carry = overflow as u64;
+    (EVEN_BITS ^ (even_starts << 1)) & follows_escape
+}
+
+/// Each bit becomes the xor of itself and every bit below it
+fn prefix_xor(mut bits: u64) -> u64 {
+    bits ^= bits << 1;
+    bits ^= bits << 2;
+    bits ^= bits << 4;
+    bits ^= bits << 8;
+    bits ^= bits << 16;
+    bits ^= bits << 32;
+    bits
+}
+
+fn is_whitespace(byte: u8) -> bool {
+    matches!(byte, b' ' | b'\t' | b'\n' | b'\r')
+}
+
+pub struct StructuralIndex {
+    positions: Vec<usize>,
+}
+
+impl StructuralIndex {
+    pub fn build(data: &[u8]) -> StructuralIndex {
+        StructuralIndex::build_with(data, Kernel::detect())
+    }
+
+    pub(crate) fn build_with(data: &[u8], kernel: Kernel) -> StructuralIndex {
+        let mut positions: Vec<usize> = Vec::with_capacity(data.len() / 8);
+        let mut escape_carry: u64 = 0;
+        let mut in_string_carry: u64 = 0;
+        let mut padded: [u8; BLOCK] = [b' '; BLOCK];
+        for (idx, chunk) in data.chunks(BLOCK).enumerate() {
+            let block: &[u8; BLOCK] = match chunk.try_into() {
+                Ok(block) => block,
+                Err(_) => {
+                    padded[..chunk.len()].copy_from_slice(chunk);
+                    &padded
+                }
+            };
+            let bits: Block = kernel.classify(block);
+            let quotes: u64 = bits.quote & !find_escaped(bits.backslash, &mut escape_carry);
+            let in_string: u64 = prefix_xor(quotes) ^ in_string_carry;
+            in_string_carry = ((in_string as i64) >> 63) as u64;
+            let mut structurals: u64 = (bits.structural & !in_string) | (quotes & in_string);
+            while structurals != 0 {
+                positions.push(idx * BLOCK + structurals.trailing_zeros() as usize);
+                structurals &= structurals - 1;
+            }
+        }
+        StructuralIndex{ positions }
+    }
+
+    /// The offset of every structural character, ascending
+    pub fn positions(&self) -> &[usize] {
+        &self.positions
+    }
+
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    /// The root value of the document
+    pub fn root(&self, data: &[u8]) -> Option<JSONValue> {
+        match data.iter().find(|byte| !is_whitespace(**byte))? {
+            b'{' | b'[' => self.container(data, 0).map(|(value, _)| value),
+            _ => JSONReader::new(data).read_value() // scalars hold no structure to index
+        }
+    }
+
+    /// Looks up [key] in the object starting at [object], comparing raw bytes like [crate::reader::Reader::find_key]
+    pub fn find_key(&self, data: &[u8], object: usize, key: &[u8]) -> Option<JSONValue> {
+        let mut idx: usize = self.at(data, object, b'{')? + 1;
+        loop {
+            let start: usize = *self.positions.get(idx)?;
+            if data.get(start) != Some(&b'"') {
+                return None // the end of the object
+            }
+            let colon: usize = *self.positions.get(idx + 1)?;
+            if data.get(colon) != Some(&b':') {
+                return None
+            }
+            let name: &[u8] = data.get(start + 1..string_end(data, colon)?)?;
+            let (value, next) = self.value_after(data, idx + 1)?;
+            if name == key {
+                return Some(value)
+            }
+            idx = self.separator(data, next)?;
+        }
+    }
+
+    /// Every element of the array starting at [array]
+    pub fn elements(&self, data: &[u8], array: usize) -> Vec<JSONValue> {
+        let mut elements: Vec<JSONValue> = vec!();
+        let mut idx: Option<usize> = self.at(data, array, b'[');
+        if idx.and_then(|idx| self.positions.get(idx + 1)).and_then(|position| data.get(*position)) == Some(&b']') {
+            return elements
+        }
+        while let Some((element, next)) = idx.and_then(|idx| self.value_after(data, idx)) {
+            elements.push(element);
+            idx = self.separator(data, next).map(|idx| idx - 1);
+        }
+        elements
+    }
+
+    /// The element at [index] of the array starting at [array]
+    pub fn element(&self, data: &[u8], array: usize, index: usize) -> Option<JSONValue> {
+        let mut idx: usize = self.at(data, array, b'[')?;
+        for _ in 0..index {
+            let (_, next) = self.value_after(data, idx)?;
+            idx = self.separator(data, next)? - 1;
+        }
+        self.value_after(data, idx).map(|(element, _)| element)
+    }
+
+    /// The index of the structural at [offset], which has to be [token]
+    fn at(&self, data: &[u8], offset: usize, token: u8) -> Option<usize> {
+        match data.get(offset) == Some(&token) {
+            true => self.positions.binary_search(&offset).ok(),
+            false => None
+        }
+    }
+
+    /// The index just past the comma at index [idx], None if it is not a comma
+    fn separator(&self, data: &[u8], idx: usize) -> Option<usize> {
+        match data.get(*self.positions.get(idx)?)? {
+            b',' => Some(idx + 1),
+            _ => None
+        }
+    }
+
+    /// Reads the value following the structural at index [idx] (a colon, comma or [),
+    /// returning it with the index of the structural after it
+    fn value_after(&self, data: &[u8], idx: usize) -> Option<(JSONValue, usize)> {
+        let mut start: usize = self.positions.get(idx)? + 1;
+        while is_whitespace(*data.get(start)?) {
+            start += 1;
+        }
+        let next: usize = *self.positions.get(idx + 1).unwrap_or(&data.len());
+        match data[start] {
+            b'{' | b'[' if start == next => self.container(data, idx + 1),
+            b'"' if start == next => {
+                let end: usize = match self.positions.get(idx + 2) {
+                    Some(position) => string_end(data, *position)?,
+                    None => string_end(data, data.len())?
+                };
+                Some((JSONValue::new(JSONType::STRING, (start + 1, end)), idx + 2))
+            },
+            token => {
+                let mut end: usize = next;
+                while end > start && is_whitespace(data[end - 1]) {
+                    end -= 1;
+                }
+                let json_type: JSONType = match token {
+                    b'n' => JSONType::NULL,
+                    b't' | b'f' => JSONType::BOOLEAN,
+                    b'0'..=b'9' | b'-' => JSONType::NUMBER,
+                    _ => return None
+                };
+                Some((JSONValue::new(json_type, (start, end)), idx + 1))
+            }
+        }
+    }
+
+    /// The container opening at index [idx] and the index after its close, found by
+    /// counting brackets in the index rather than reading the container
+    fn container(&self, data: &[u8], idx: usize) -> Option<(JSONValue, usize)> {
+        let start: usize = self.positions[idx];
+        let json_type: JSONType = match data[start] {
+            b'{' => JSONType::OBJECT,
+            _ => JSONType::ARRAY
+        };
+        let mut depth: usize = 0;
+        for (close, position) in self.positions.iter().enumerate().skip(idx) {
+            match data[*position] {
+                b'{' | b'[' => depth += 1,
+                b'}' | b']' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Some((JSONValue::new(json_type, (start, position + 1)), close + 1))
+                    }
+                },
+                _ => {}
+            }
+        }
+        None
+    }
+}
+
+/// Where the contents of a string end, given the structural following it
+fn string_end(data: &[u8], next: usize) -> Option<usize> {
+    let mut end: usize = next;
+    while end > 0 && is_whitespace(*data.get(end - 1)?) {
+        end -= 1;
+    }
+    match end > 0 && data[end - 1] == b'"' {
+        true => Some(end - 1),
+        false => None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::reader::{JSONType, JSONValue};
+    use crate::structural::{Kernel, StructuralIndex};
+
+    const JSON: &[u8] = r#"{"name":"alex","hobbies":["cooking","guitar"],"nested":{"foo":"bar","herp":false}}"#.as_bytes();
+
+    /// Every kernel that can run here
+    fn kernels() -> Vec<Kernel> {
+        let mut kernels: Vec<Kernel> = vec!(Kernel::Scalar);
+        #[cfg(target_arch = "x86_64")]
+        {
+            kernels.push(Kernel::Sse2);
+            if is_x86_feature_detected!("avx2") {
+                kernels.push(Kernel::Avx2);
+            }
+        }
+        kernels
+    }
+
+    /// Walks the bytes one at a time to find the structurals the index should hold
+    fn expected(data: &[u8]) -> Vec<usize> {
+        let (mut positions, mut in_string, mut escaped): (Vec<usize>, bool, bool) = (vec!(), false, false);
+        for (idx, byte) in data.iter().enumerate() {
+            if in_string {
+                match (escaped, byte) {
+                    (true, _) => escaped = false,
+                    (false, b'\\') => escaped = true,
+                    (false, b'"') => in_string = false,
+                    _ => {}
+                }
+            } else if *byte == b'"' {
+                in_string = true;
+                positions.push(idx);
+            } else if b"{}[]:,".contains(byte) {
+                positions.push(idx);
+            }
+        }
+        positions
+    }
+
+    #[test]
+    fn test_positions() {
+        let mut documents: Vec<Vec<u8>> = vec!(JSON.to_vec(), br#"{"a\"b": "\\", "c": ["]\\\"", {}]}"#.to_vec());
+        // backslash runs and strings straddling block boundaries
+        for pad in 55..70 {
+            let mut json: Vec<u8> = b"[\"".to_vec();
+            json.extend(std::iter::repeat_n(b'x', pad));
+            json.extend_from_slice(br#"\\\\\",[\\", {"k": "v\"}"}, "\\\\\\\"]"]"#);
+            documents.push(json);
+        }
+        for json in documents {
+            for kernel in kernels() {
+                assert_eq!(StructuralIndex::build_with(&json, kernel).positions(), expected(&json).as_slice(),
+                           "{:?} on {}", kernel, String::from_utf8_lossy(&json));
+            }
+        }
+    }
+
+    #[test]
+    fn test_find_key() {
+        let index: StructuralIndex = StructuralIndex::build(JSON);
+        let root: JSONValue = index.root(JSON).unwrap();
+        assert_eq!(root.range(), (0, JSON.len()));
+        assert_eq!(index.find_key(JSON, 0, b"name"), Some(JSONValue::new(JSONType::STRING, (9, 13))));
+        let nested: JSONValue = index.find_key(JSON, 0, b"nested").unwrap();
+        assert_eq!(nested.range(), (55, JSON.len() - 1));
+        assert_eq!(index.find_key(JSON, nested.range().0, b"herp"), Some(JSONValue::new(JSONType::BOOLEAN, (75, 80))));
+        assert_eq!(index.find_key(JSON, 0, b"foo"), None);
+        assert_eq!(index.find_key(JSON, 1, b"name"), None);
+        assert_eq!(StructuralIndex::build(b" 42 ").root(b" 42 "), Some(JSONValue::new(JSONType::NUMBER, (1, 3))));
+    }
+
+    #[test]
+    fn test_elements() {
+        let json: &[u8] = br#"[ 1 , "a" , null, [true], {"b": -2.5} ]"#;
+        let index: StructuralIndex = StructuralIndex::build(json);
+        let elements: Vec<&[u8]> = index.elements(json, 0).iter()
+            .map(|value| &json[value.range().0..value.range().1])
+            .collect();
+        assert_eq!(elements, vec!(b"1".as_ref(), b"a", b"null", b"[true]", br#"{"b": -2.5}"#));
+        assert_eq!(index.element(json, 0, 3).map(|value| value.json_type()), Some(JSONType::ARRAY));
+        assert_eq!(index.element(json, 0, 5), None);
+        assert!(StructuralIndex::build(b"[ ]").elements(b"[ ]", 0).is_empty());
+    }
+}
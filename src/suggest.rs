@@ -0,0 +1,63 @@
+//! "Did you mean" suggestions for keys that could not be found.
+//!
+//! Candidates are ranked by their Levenshtein edit distance to the requested key,
+//! e.g. `hobbys` is two edits away from `hobbies`.
+
+use std::collections::HashSet;
+
+/// Computes the Levenshtein edit distance between two byte strings
+pub fn edit_distance(a: &[u8], b: &[u8]) -> usize {
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current: Vec<usize> = vec![0; b.len() + 1];
+    for (i, x) in a.iter().enumerate() {
+        current[0] = i + 1;
+        for (j, y) in b.iter().enumerate() {
+            let substitution: usize = previous[j] + if x == y { 0 } else { 1 };
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[b.len()]
+}
+
+/// The largest distance still considered a typo of [key] rather than a different key
+fn threshold(key: &[u8]) -> usize {
+    std::cmp::max(1, key.len().div_ceil(3))
+}
+
+/// Returns the candidates close enough to [key] to be a likely typo, nearest first.
+/// Candidates at the same distance keep their document order, repeats are suggested once.
+pub fn closest<'a>(key: &[u8], candidates: &[&'a [u8]]) -> Vec<&'a [u8]> {
+    let limit: usize = threshold(key);
+    let mut seen: HashSet<&[u8]> = HashSet::new();
+    let mut scored: Vec<(usize, &'a [u8])> = candidates.iter()
+        .filter(|candidate| seen.insert(candidate))
+        .map(|candidate| (edit_distance(key, candidate), *candidate))
+        .filter(|(distance, _)| *distance <= limit)
+        .collect();
+    scored.sort_by_key(|(distance, _)| *distance);
+    scored.into_iter().map(|(_, candidate)| candidate).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::suggest::{closest, edit_distance};
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance(b"hobbys", b"hobbies"), 2);
+        assert_eq!(edit_distance(b"", b"age"), 3);
+        assert_eq!(edit_distance(b"name", b"name"), 0);
+    }
+
+    #[test]
+    fn test_closest() {
+        let keys: Vec<&[u8]> = vec!(b"name", b"boy", b"age", b"hobbies", b"nested");
+        assert_eq!(closest(b"hobbys", &keys), vec!(b"hobbies".as_ref()));
+        assert_eq!(closest(b"ag", &keys), vec!(b"age".as_ref()));
+        assert!(closest(b"completely", &keys).is_empty());
+        // a repeated key with another at the same distance between its copies
+        let repeated: Vec<&[u8]> = vec!(b"nme", b"game", b"nme");
+        assert_eq!(closest(b"name", &repeated), vec!(b"nme".as_ref(), b"game"));
+    }
+}
@@ -0,0 +1,277 @@
+//! A reusable index of a document's values, built once and queried many times.
+//!
+//! The tape holds one entry per value in document order with its byte range, the key
+//! it is stored under and the index of the entry just past everything nested in it.
+//! Every container also gets a lookup table: array elements by position, object
+//! members by a hash of their raw key. A [Query] then costs one table lookup per path
+//! component, and skipping a container is a single jump to its end.
+
+use crate::duplicates::DuplicateKeys;
+use crate::limits::Limits;
+use crate::parser::{Event, ParseError, Parser};
+use crate::query::Query;
+use crate::reader::{JSONRange, JSONReader, JSONType, JSONValue};
+use crate::string::KeyMatch;
+
+/// 64 bit FNV-1a
+pub(crate) fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash: u64, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Entry {
+    pub(crate) value: JSONValue,
+    /// The raw key of an object member, quotes excluded
+    pub(crate) key: Option<JSONRange>,
+    /// The index of the first entry after this value and everything nested in it
+    pub(crate) next: usize,
+    /// Where the container's children are listed in [Tape::lookup]
+    pub(crate) children: JSONRange,
+}
+
+impl Entry {
+    pub fn value(&self) -> JSONValue {
+        self.value
+    }
+
+    pub fn key(&self) -> Option<JSONRange> {
+        self.key
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Tape {
+    pub(crate) entries: Vec<Entry>,
+    /// Per container, (position, entry) of every element for arrays and
+    /// (key hash, entry) of every member sorted by hash for objects
+    pub(crate) lookup: Vec<(u64, usize)>,
+}
+
+impl Tape {
+    /// Parses [json] once, recording every value. Fails like [crate::validation::Validator::check]
+    /// but without limits, tapes are built for large trusted files, see [Tape::build_with_limits].
+    pub fn build(json: &[u8]) -> Result<Tape, ParseError> {
+        Tape::build_with_limits(json, Limits::unlimited())
+    }
+
+    /// [Tape::build], failing once the document goes past [limits]
+    pub fn build_with_limits(json: &[u8], limits: Limits) -> Result<Tape, ParseError> {
+        let mut tape: Tape = Tape{ entries: vec!(), lookup: vec!() };
+        // the entry of every open container with its children so far
+        let mut stack: Vec<(usize, Vec<(u64, usize)>)> = vec!();
+        let mut key: Option<JSONRange> = None;
+        let mut reader: JSONReader = JSONReader::new(json);
+        for token in Parser::new(&mut reader).with_limits(limits) {
+            let token = token?;
+            let json_type: JSONType = match token.event {
+                Event::Key => {
+                    key = Some(token.range);
+                    continue
+                },
+                Event::EndObject | Event::EndArray => {
+                    if let Some((idx, mut children)) = stack.pop() {
+                        if token.event == Event::EndObject {
+                            children.sort_unstable();
+                        }
+                        let next: usize = tape.entries.len();
+                        let entry: &mut Entry = &mut tape.entries[idx];
+                        entry.value.range.1 = token.range.1;
+                        entry.next = next;
+                        entry.children = (tape.lookup.len(), tape.lookup.len() + children.len());
+                        tape.lookup.extend(children);
+                    }
+                    continue
+                },
+                Event::StartObject => JSONType::OBJECT,
+                Event::StartArray => JSONType::ARRAY,
+                Event::Value(json_type) => json_type,
+            };
+            let idx: usize = tape.entries.len();
+            let key: Option<JSONRange> = key.take();
+            if let Some((_, children)) = stack.last_mut() {
+                let slot: u64 = match key {
+                    Some((start, end)) => fnv1a(&json[start..end]),
+                    None => children.len() as u64
+                };
+                children.push((slot, idx));
+            }
+            tape.entries.push(Entry{ value: JSONValue::new(json_type, token.range), key, next: idx + 1, children: (0, 0) });
+            if !json_type.is_scalar() {
+                stack.push((idx, vec!()));
+            }
+        }
+        Ok(tape)
+    }
+
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn root(&self) -> Option<JSONValue> {
+        self.entries.first().map(|entry| entry.value)
+    }
+
+    /// The entry of the member [key] of the object at entry [object], the first one if it repeats
+    pub fn find_key(&self, json: &[u8], object: usize, key: &[u8]) -> Option<usize> {
+        self.matching_keys(json, object, key).next()
+    }
+
+    /// [Tape::find_key], settling a key that repeats as [policy] says. Keys are compared with
+    /// their escapes resolved, which the raw key hashes cannot answer, so every member is read.
+    pub fn find_key_with_policy(&self, json: &[u8], object: usize, key: &[u8], policy: DuplicateKeys) -> Result<Option<usize>, ParseError> {
+        if self.entries.get(object).map(|entry| entry.value.json_type) != Some(JSONType::OBJECT) {
+            return Ok(None)
+        }
+        policy.resolve(self.children(object).into_iter()
+            .filter_map(|idx| self.entries[idx].key.map(|(start, end)| (start, end, idx)))
+            .filter(|(start, end, _)| json.get(*start..*end).is_some_and(|raw| KeyMatch::Decoded.matches(raw, key)))
+            .map(|(start, _, idx)| (start, idx))
+            .collect())
+    }
+
+    /// Every member of [object] named [key], in document order
+    fn matching_keys<'t>(&'t self, json: &'t [u8], object: usize, key: &'t [u8]) -> impl Iterator<Item = usize> + 't {
+        let members: &[(u64, usize)] = match self.entries.get(object) {
+            Some(entry) if entry.value.json_type == JSONType::OBJECT => &self.lookup[entry.children.0..entry.children.1],
+            _ => &[]
+        };
+        let hash: u64 = fnv1a(key);
+        let first: usize = members.partition_point(|(slot, _)| *slot < hash);
+        members[first..].iter()
+            .take_while(move |(slot, _)| *slot == hash)
+            .map(|(_, idx)| *idx)
+            .filter(move |idx| self.entries[*idx].key.is_some_and(|(start, end)| json.get(start..end) == Some(key)))
+    }
+
+    /// The entry of the element at [index] of the array at entry [array]
+    pub fn element(&self, array: usize, index: usize) -> Option<usize> {
+        let entry: &Entry = self.entries.get(array)?;
+        match entry.value.json_type {
+            JSONType::ARRAY => self.lookup[entry.children.0..entry.children.1].get(index).map(|(_, idx)| *idx),
+            _ => None
+        }
+    }
+
+    /// The entries directly inside the container at entry [container], in document order
+    pub fn children(&self, container: usize) -> Vec<usize> {
+        let mut children: Vec<usize> = vec!();
+        if let Some(entry) = self.entries.get(container) {
+            let mut idx: usize = container + 1;
+            while idx < entry.next {
+                children.push(idx);
+                idx = self.entries[idx].next;
+            }
+        }
+        children
+    }
+
+    /// Where the value starting at [offset] ends, strings by their first content byte
+    pub fn skip(&self, offset: usize) -> Option<usize> {
+        let idx: usize = self.entries.binary_search_by_key(&offset, |entry| entry.value.range.0).ok()?;
+        Some(self.entries[idx].value.range.1)
+    }
+
+    /// Resolves [query] like [crate::engine::select] without reading the document again
+    pub fn select(&self, json: &[u8], query: &Query) -> Vec<JSONValue> {
+        if self.entries.is_empty() {
+            return vec!()
+        }
+        let mut idx: usize = 0;
+        for selector in query.components.iter().filter(|selector| !selector.path.is_empty()) {
+            let next: Option<usize> = match selector.index() {
+                Some(index) => self.element(idx, index),
+                None => self.find_key(json, idx, selector.path)
+            };
+            idx = match next {
+                None => return vec!(),
+                Some(next) => next
+            };
+        }
+        let value: JSONValue = self.entries[idx].value;
+        if query.filters.is_empty() {
+            return vec!(value)
+        }
+        let candidates: Vec<JSONValue> = match value.json_type {
+            JSONType::ARRAY => self.children(idx).into_iter().map(|child| self.entries[child].value).collect(),
+            _ => vec!(value)
+        };
+        candidates.into_iter().filter(|candidate| query.keeps(candidate.json_type)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::duplicates::DuplicateKeys;
+    use crate::engine::select;
+    use crate::limits::{Limit, Limits};
+    use crate::parser::{ErrorKind, ParseError};
+    use crate::query::Query;
+    use crate::reader::JSONType;
+    use crate::tape::Tape;
+
+    const JSON: &[u8] = r#"{"mixed": ["a", 1, null, {"b": 2}, "c", [true]], "name": "alex", "n": {"e": {}, "e": 1}}"#.as_bytes();
+
+    #[test]
+    fn test_select_matches_engine() {
+        let tape: Tape = Tape::build(JSON).unwrap();
+        for query in ["name", "mixed", "mixed.[3].b", "mixed.[5].[0]", "mixed | scalars", "n.e", "n.x", "mixed.[9]", "name.[0]"] {
+            assert_eq!(tape.select(JSON, &Query::from(query)), select(JSON, &Query::from(query)), "{}", query);
+        }
+    }
+
+    #[test]
+    fn test_structure() {
+        let tape: Tape = Tape::build(JSON).unwrap();
+        assert_eq!(tape.root().unwrap().range(), (0, JSON.len()));
+        let keys: Vec<&[u8]> = tape.children(0).into_iter()
+            .map(|child| tape.entries()[child].key().unwrap())
+            .map(|(start, end)| &JSON[start..end])
+            .collect();
+        assert_eq!(keys, vec!(b"mixed".as_ref(), b"name", b"n"));
+        assert_eq!(tape.children(1).len(), 6);
+        assert_eq!(tape.skip(10), Some(47));
+        assert_eq!(tape.skip(11), None);
+        assert_eq!(tape.entries()[tape.element(1, 3).unwrap()].value().json_type(), JSONType::OBJECT);
+    }
+
+    #[test]
+    fn test_duplicate_keys() {
+        let tape: Tape = Tape::build(JSON).unwrap();
+        let n: usize = tape.find_key(JSON, 0, b"n").unwrap();
+        let json_type = |policy: DuplicateKeys| tape.find_key_with_policy(JSON, n, b"e", policy)
+            .map(|found| found.map(|idx| tape.entries()[idx].value().json_type()));
+        assert_eq!(json_type(DuplicateKeys::FirstWins), Ok(Some(JSONType::OBJECT)));
+        assert_eq!(json_type(DuplicateKeys::LastWins), Ok(Some(JSONType::NUMBER)));
+        assert_eq!(json_type(DuplicateKeys::Error), Err(ParseError{ kind: ErrorKind::DuplicateKey(72), offset: 81 }));
+        assert_eq!(tape.find_key_with_policy(JSON, 0, b"name", DuplicateKeys::Error).map(|found| found.is_some()), Ok(true));
+        let escaped: &[u8] = br#"{"a": 1, "\u0061": 2}"#;
+        let tape: Tape = Tape::build(escaped).unwrap();
+        assert_eq!(tape.find_key_with_policy(escaped, 0, b"a", DuplicateKeys::Error), Err(ParseError{ kind: ErrorKind::DuplicateKey(2), offset: 10 }));
+        assert_eq!(tape.find_key_with_policy(escaped, 0, b"a", DuplicateKeys::LastWins), Ok(Some(2)));
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert_eq!(Tape::build(b"[1, 2").unwrap_err().kind, ErrorKind::UnexpectedEnd);
+    }
+
+    #[test]
+    fn test_limits() {
+        let limits: Limits = Limits{ max_document_size: 16, ..Limits::default() };
+        assert_eq!(Tape::build_with_limits(JSON, limits).unwrap_err().kind, ErrorKind::LimitExceeded(Limit::DocumentSize));
+        assert!(Tape::build_with_limits(br#"{"a": [1]}"#, limits).is_ok());
+        let deep: Vec<u8> = [vec!(b'['; 200), vec!(b']'; 200)].concat();
+        assert_eq!(Tape::build(&deep).map(|tape| tape.entries.len()), Ok(200));
+        assert_eq!(Tape::build(JSON), Tape::build_with_limits(JSON, Limits::unlimited()));
+    }
+}
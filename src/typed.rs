@@ -0,0 +1,226 @@
+//! Typed access to the values a [crate::reader::Reader] finds.
+//!
+//! A [JSONValue] only knows its type and where it lives, so every accessor takes the
+//! document the value was read from.
+
+use std::borrow::Cow;
+use std::fmt;
+use std::str::FromStr;
+use crate::decimal::Decimal;
+use crate::reader::{JSONType, JSONValue};
+use crate::string::{self, StringError};
+use crate::validation::is_number;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ValueError {
+    /// The value is not of the type the accessor reads
+    Mismatch { expected: JSONType, found: JSONType },
+    /// The number does not fit in the requested type
+    Overflow,
+    /// The number has a fraction or exponent, but an integer was requested
+    NotAnInteger,
+    /// The bytes of the value do not form what its type says they should
+    Malformed,
+    /// The value's range lies outside of the document it was read with
+    OutOfBounds,
+    /// A valid number whose power of ten lies beyond what [Decimal] holds, ±2^63
+    ExponentOutOfRange,
+    String(StringError),
+}
+
+impl fmt::Display for ValueError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValueError::Mismatch { expected, found } => write!(f, "expected {:?}, found {:?}", expected, found),
+            ValueError::Overflow => write!(f, "number out of range"),
+            ValueError::NotAnInteger => write!(f, "number is not an integer"),
+            ValueError::Malformed => write!(f, "malformed value"),
+            ValueError::OutOfBounds => write!(f, "value lies outside of the document"),
+            ValueError::ExponentOutOfRange => write!(f, "exponent out of range"),
+            ValueError::String(error) => error.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for ValueError {}
+
+impl From<StringError> for ValueError {
+    fn from(error: StringError) -> ValueError {
+        ValueError::String(error)
+    }
+}
+
+/// A number exactly as it was written in the document, nothing is lost to rounding
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Number<'a> {
+    lexeme: &'a str,
+}
+
+impl<'a> Number<'a> {
+    /// Fails unless [lexeme] is exactly one JSON number
+    pub fn parse(lexeme: &'a [u8]) -> Result<Number<'a>, ValueError> {
+        if !is_number(lexeme) {
+            return Err(ValueError::Malformed)
+        }
+        // the grammar only allows ASCII
+        std::str::from_utf8(lexeme).map(|lexeme| Number{ lexeme }).map_err(|_| ValueError::Malformed)
+    }
+
+    /// The exact decimal text of the number
+    pub fn as_str(&self) -> &'a str {
+        self.lexeme
+    }
+
+    /// Whether the number has neither a fraction nor an exponent
+    pub fn is_integer(&self) -> bool {
+        !self.lexeme.contains(['.', 'e', 'E'])
+    }
+
+    pub fn as_i64(&self) -> Result<i64, ValueError> {
+        self.signed()
+    }
+
+    pub fn as_u64(&self) -> Result<u64, ValueError> {
+        self.unsigned()
+    }
+
+    pub fn as_i128(&self) -> Result<i128, ValueError> {
+        self.signed()
+    }
+
+    pub fn as_u128(&self) -> Result<u128, ValueError> {
+        self.unsigned()
+    }
+
+    /// Parses the digits directly, so integers are exact or an overflow, never rounded
+    fn signed<T: FromStr>(&self) -> Result<T, ValueError> {
+        if !self.is_integer() {
+            return Err(ValueError::NotAnInteger)
+        }
+        self.lexeme.parse().map_err(|_| ValueError::Overflow)
+    }
+
+    fn unsigned<T: FromStr + Default>(&self) -> Result<T, ValueError> {
+        if !self.is_integer() {
+            return Err(ValueError::NotAnInteger)
+        }
+        match self.lexeme.strip_prefix('-') {
+            Some(magnitude) if magnitude.bytes().all(|digit| digit == b'0') => Ok(T::default()),
+            Some(_) => Err(ValueError::Overflow),
+            None => self.lexeme.parse().map_err(|_| ValueError::Overflow)
+        }
+    }
+
+    /// The nearest f64, numbers too large for one are an overflow rather than infinity
+    pub fn as_f64(&self) -> Result<f64, ValueError> {
+        match self.lexeme.parse::<f64>() {
+            Ok(number) if number.is_finite() => Ok(number),
+            Ok(_) => Err(ValueError::Overflow),
+            Err(_) => Err(ValueError::Malformed)
+        }
+    }
+}
+
+impl JSONValue {
+    /// The bytes of the value within [data], strings exclude their quotes
+    pub(crate) fn bytes<'a>(&self, data: &'a [u8]) -> Result<&'a [u8], ValueError> {
+        data.get(self.range.0..self.range.1).ok_or(ValueError::OutOfBounds)
+    }
+
+    fn expect(&self, expected: JSONType) -> Result<(), ValueError> {
+        match self.json_type == expected {
+            true => Ok(()),
+            false => Err(ValueError::Mismatch { expected, found: self.json_type })
+        }
+    }
+
+    /// The string with its escapes resolved, borrowed from [data] when there are none
+    pub fn as_str<'a>(&self, data: &'a [u8]) -> Result<Cow<'a, str>, ValueError> {
+        self.expect(JSONType::STRING)?;
+        Ok(string::decode(self.bytes(data)?)?)
+    }
+
+    pub fn as_bool(&self, data: &[u8]) -> Result<bool, ValueError> {
+        self.expect(JSONType::BOOLEAN)?;
+        match self.bytes(data)? {
+            b"true" => Ok(true),
+            b"false" => Ok(false),
+            _ => Err(ValueError::Malformed)
+        }
+    }
+
+    /// The number exactly as written, for lossless decimal access
+    pub fn as_number<'a>(&self, data: &'a [u8]) -> Result<Number<'a>, ValueError> {
+        self.expect(JSONType::NUMBER)?;
+        Number::parse(self.bytes(data)?)
+    }
+
+    pub fn as_i64(&self, data: &[u8]) -> Result<i64, ValueError> {
+        self.as_number(data)?.as_i64()
+    }
+
+    pub fn as_u64(&self, data: &[u8]) -> Result<u64, ValueError> {
+        self.as_number(data)?.as_u64()
+    }
+
+    pub fn as_i128(&self, data: &[u8]) -> Result<i128, ValueError> {
+        self.as_number(data)?.as_i128()
+    }
+
+    pub fn as_u128(&self, data: &[u8]) -> Result<u128, ValueError> {
+        self.as_number(data)?.as_u128()
+    }
+
+    /// The exact value of a number of any size, see [Decimal]
+    pub fn as_decimal(&self, data: &[u8]) -> Result<Decimal, ValueError> {
+        self.as_number(data)?.as_decimal()
+    }
+
+    pub fn as_f64(&self, data: &[u8]) -> Result<f64, ValueError> {
+        self.as_number(data)?.as_f64()
+    }
+
+    pub fn is_null(&self) -> bool {
+        self.json_type == JSONType::NULL
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::reader::{JSONReader, JSONType, JSONValue, Reader};
+    use crate::typed::ValueError;
+
+    const JSON: &[u8] = br#"{"name":"al\"ex","boy":true,"age":32,"debt":-18446744073709551616,"ratio":2.5,"nothing":null}"#;
+
+    fn value(key: &str) -> JSONValue {
+        let mut reader: JSONReader = JSONReader::new(JSON);
+        reader.find_key(key.as_bytes()).unwrap();
+        reader.read_value().unwrap()
+    }
+
+    #[test]
+    fn test_accessors() {
+        assert_eq!(value("name").as_str(JSON).unwrap(), "al\"ex");
+        assert_eq!(value("boy").as_bool(JSON), Ok(true));
+        assert_eq!(value("age").as_i64(JSON), Ok(32));
+        assert_eq!(value("age").as_u64(JSON), Ok(32));
+        assert_eq!(value("ratio").as_f64(JSON), Ok(2.5));
+        assert_eq!(value("debt").as_i128(JSON), Ok(-18446744073709551616));
+        assert_eq!(value("debt").as_u128(JSON), Err(ValueError::Overflow));
+        assert_eq!(value("age").as_u128(JSON), Ok(32));
+        assert_eq!(value("ratio").as_decimal(JSON).map(|ratio| ratio.to_string()), Ok("2.5".to_string()));
+        assert_eq!(value("ratio").as_number(JSON).unwrap().as_str(), "2.5");
+        assert!(value("nothing").is_null());
+        assert!(!value("age").is_null());
+    }
+
+    #[test]
+    fn test_accessor_errors() {
+        assert_eq!(value("age").as_str(JSON), Err(ValueError::Mismatch { expected: JSONType::STRING, found: JSONType::NUMBER }));
+        assert_eq!(value("name").as_i64(JSON), Err(ValueError::Mismatch { expected: JSONType::NUMBER, found: JSONType::STRING }));
+        assert_eq!(value("debt").as_i64(JSON), Err(ValueError::Overflow));
+        assert_eq!(value("debt").as_u64(JSON), Err(ValueError::Overflow));
+        assert_eq!(value("ratio").as_i64(JSON), Err(ValueError::NotAnInteger));
+        assert_eq!(value("age").as_i64(b"{}"), Err(ValueError::OutOfBounds));
+    }
+}
@@ -1,106 +1,148 @@
-use std::error::Error;
-use crate::reader::{JSONReader, Reader};
+use crate::duplicates::DuplicateKeys;
+use crate::limits::Limits;
+use crate::parser::{Event, Parser, ParseError, Token};
+use crate::reader::Reader;
+
+/// Whether [lexeme] is exactly one number as the JSON grammar defines it:
+/// '-'? (0 | [1-9][0-9]*) ('.' [0-9]+)? ([Ee] [+-]? [0-9]+)?
+pub fn is_number(lexeme: &[u8]) -> bool {
+    fn digits(lexeme: &[u8], from: usize) -> usize {
+        lexeme[from..].iter().take_while(|digit| digit.is_ascii_digit()).count()
+    }
+    let mut idx: usize = 0;
+    if lexeme.first() == Some(&b'-') {
+        idx += 1;
+    }
+    match lexeme.get(idx) {
+        Some(b'0') => idx += 1,
+        Some(b'1'..=b'9') => idx += digits(lexeme, idx),
+        _ => return false
+    }
+    if lexeme.get(idx) == Some(&b'.') {
+        let fraction: usize = digits(lexeme, idx + 1);
+        if fraction == 0 {
+            return false
+        }
+        idx += 1 + fraction;
+    }
+    if let Some(b'e') | Some(b'E') = lexeme.get(idx) {
+        idx += 1;
+        if let Some(b'+') | Some(b'-') = lexeme.get(idx) {
+            idx += 1;
+        }
+        let exponent: usize = digits(lexeme, idx);
+        if exponent == 0 {
+            return false
+        }
+        idx += exponent;
+    }
+    idx == lexeme.len()
+}
 
 /// Validates the read data
 pub trait Validator {
 
+    /// Whether the reader holds exactly one valid document from its current position.
+    /// Documents past [Limits::default], e.g. over 256 MiB or nested deeper than 128, are
+    /// invalid here; they used to pass. Trusted ones need [Validator::check_with_limits].
     fn validate(&mut self) -> bool;
 
+    /// Validates like [Validator::validate], reporting where the document goes wrong.
+    /// Documents past [Limits::default] fail with [crate::parser::ErrorKind::LimitExceeded]
+    /// where they used to pass, [Limits::unlimited] through [Validator::check_with_limits]
+    /// validates them like before.
+    fn check(&mut self) -> Result<(), ParseError>;
+
+    /// Validates any number of documents following each other, returning how many there were
+    fn check_concatenated(&mut self) -> Result<usize, ParseError>;
+
+    /// Validates one document that may use JSONC and JSON5 syntax, see [Parser::lenient]
+    fn check_lenient(&mut self) -> Result<(), ParseError>;
+
+    /// Validates like [Validator::check], failing once the document goes past [limits]
+    /// instead of the default ones
+    fn check_with_limits(&mut self, limits: Limits) -> Result<(), ParseError>;
+
+    /// Validates like [Validator::check], also failing on a key that repeats within its
+    /// object. The error is at the repeat and carries where the key first appeared.
+    fn check_unique_keys(&mut self) -> Result<(), ParseError>;
+
+    /// Assumes the opening brace has been read, consumes through the closing one
+    #[deprecated(note = "use Validator::check, or a Parser to see the object's tokens")]
     fn validate_object(&mut self) -> bool;
 
+    /// Assumes the opening bracket has been read, consumes through the closing one
+    #[deprecated(note = "use Validator::check, or a Parser to see the array's tokens")]
     fn validate_array(&mut self) -> bool;
 
+    /// Assumes the opening quote has been read, consumes through the closing quote
     fn validate_string(&mut self) -> bool;
 
+    /// Assumes the first byte of the number has been read
+    fn validate_number(&mut self) -> bool;
+
     fn validate_boolean(&mut self) -> bool;
 
     fn validate_null(&mut self) -> bool;
 }
 
-impl Validator for JSONReader<'_> {
+impl<T: Reader + ?Sized> Validator for T {
     fn validate(&mut self) -> bool {
-        while let Some(token) = self.next() {
-            match token {
-                b'"' => {
-                    self.validate_string();
-                },
-                b't' | b'f' => {
-                    self.validate_boolean();
-                },
-                b'n' => {
-                    self.validate_null();
-                },
-                b'{' => {
-                    self.validate_object();
-                },
-                b'[' => {},
-                _ => {}
-            }
+        self.check().is_ok()
+    }
+
+    fn check(&mut self) -> Result<(), ParseError> {
+        for token in Parser::new(self) {
+            token?;
         }
-        true
+        Ok(())
     }
 
-    fn validate_object(&mut self) -> bool {
-        while let Some(token) = self.next() {
-            match token {
-                b'}' => return true,
-                b' ' | b'\t' | b'\n' | b'r' => (), // whitespace
-                b'"' => { // we're now into a candidate for a key"
-                    if !self.validate_string() {
-                        return false
-                    }
-                    while let Some(maybe_colon) = self.next() {
-                        match maybe_colon {
-                            b':' => break,
-                            b' ' | b'\t' | b'\n' | b'r' => continue,
-                            _ => return false
-                        }
-                    }
-                    if !self.validate() {
-                        return false
-                    }
-                    while let Some(maybe_comma) = self.next() {
-                        match maybe_comma {
-                            b',' => break,
-                            b' ' | b'\t' | b'\n' | b'r' => continue,
-                            b'}' => return true,
-                            _ => return false
-                        }
-                    }
-                },
-                _ => return false
+    fn check_concatenated(&mut self) -> Result<usize, ParseError> {
+        let mut documents: usize = 0;
+        for token in Parser::concatenated(self) {
+            let token: Token = token?;
+            if token.depth == 0 && !matches!(token.event, Event::EndObject | Event::EndArray) {
+                documents += 1;
             }
         }
-        false // would have expected to hit the case b'}' => return true
+        Ok(documents)
     }
 
-    fn validate_array(&mut self) -> bool {
-        while let Some(token) = self.next() {
-            match token {
-                b']' => return true,
-                b' ' | b'\t' | b'\n' | b'r' => (), // whitespace
-                _ => {
-                    if !self.validate() {
-                        return false
-                    }
-                    while let Some(maybe_comma) = self.next() {
-                        match maybe_comma {
-                            b',' => break,
-                            b' ' | b'\t' | b'\n' | b'r' => continue,
-                            b']' => return true,
-                            _ => return false
-                        }
-                    }
-                }
-            }
+    fn check_lenient(&mut self) -> Result<(), ParseError> {
+        for token in Parser::lenient(self) {
+            token?;
+        }
+        Ok(())
+    }
+
+    fn check_with_limits(&mut self, limits: Limits) -> Result<(), ParseError> {
+        for token in Parser::new(self).with_limits(limits) {
+            token?;
         }
-        false
+        Ok(())
+    }
+
+    fn check_unique_keys(&mut self) -> Result<(), ParseError> {
+        for token in Parser::new(self).with_duplicate_keys(DuplicateKeys::Error) {
+            token?;
+        }
+        Ok(())
+    }
+
+    fn validate_object(&mut self) -> bool {
+        validate_container(self, b'{', Event::EndObject)
+    }
+
+    fn validate_array(&mut self) -> bool {
+        validate_container(self, b'[', Event::EndArray)
     }
 
     fn validate_string(&mut self) -> bool {
         while let Some(token) = self.next() {
             match token {
                 b'"' => return true,
+                0x00..=0x1F => return false, // control characters must be escaped
                 b'\\' => { // escape characters
                     let escaped: Option<u8> = self.next();
                     if escaped.is_none() {
@@ -110,13 +152,9 @@ impl Validator for JSONReader<'_> {
                         b'"' | b'\\' | b'/' | b'b' | b'f' | b'n' | b'r' | b't' => true,
                         b'u' => { // \uHEX,HEX,HEX,HEX
                             for _ in 0..4 {
-                                if let Some(maybe_hex) = self.next() {
-                                    match maybe_hex {
-                                        b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' => (),
-                                        _ => return false
-                                    }
-                                } else {
-                                    return false
+                                match self.next() {
+                                    Some(b'a'..=b'f') | Some(b'A'..=b'F') | Some(b'0'..=b'9') => (),
+                                    _ => return false
                                 }
                             }
                             true
@@ -130,7 +168,14 @@ impl Validator for JSONReader<'_> {
                 _ => ()
             }
         }
-        true
+        false // never terminated
+    }
+
+    fn validate_number(&mut self) -> bool {
+        match self.read_number() {
+            None => false,
+            Some(range) => self.select(range).is_some_and(is_number)
+        }
     }
 
     fn validate_boolean(&mut self) -> bool {
@@ -149,34 +194,100 @@ impl Validator for JSONReader<'_> {
     }
 }
 
+/// Assumes [open] has just been read, parses through the container it opens
+fn validate_container<R: Reader + ?Sized>(reader: &mut R, open: u8, close: Event) -> bool {
+    if reader.current() != Some(open) {
+        return false
+    }
+    let position: usize = reader.position();
+    reader.seek(position); // the parser reads the container from its opening token
+    for token in Parser::new(reader) {
+        match token {
+            Err(_) => return false,
+            Ok(token) if token.depth == 0 && token.event == close => return true,
+            Ok(_) => ()
+        }
+    }
+    false
+}
+
 #[cfg(test)]
 mod tests {
+    use crate::parser::{ErrorKind, ParseError};
     use crate::reader::{JSONReader, Reader};
-    use crate::validation::Validator;
+    use crate::validation::{is_number, Validator};
 
     const JSON_BOOLEAN: &[u8] = r#"{"foo": true, "bar": false}"#.as_bytes();
     const JSON_NULL: &[u8] = r#"{"is": null, "isnt":nil}"#.as_bytes();
-    const JSON_STRING: &[u8] = r#"{}"#.as_bytes();
 
     #[test]
     fn test_validate_boolean() {
         let mut reader: JSONReader = JSONReader::new(JSON_BOOLEAN);
         reader.seek(9);
-        assert_eq!(reader.validate_boolean(), true);
+        assert!(reader.validate_boolean());
         reader.seek(22);
-        assert_eq!(reader.validate_boolean(), true);
+        assert!(reader.validate_boolean());
     }
 
     #[test]
     fn test_validate_null() {
         let mut reader: JSONReader = JSONReader::new(JSON_NULL);
-        reader.seek(7);
-        assert_eq!(reader.validate_null(), true);
+        reader.seek(8);
+        assert!(reader.validate_null());
         reader.seek(44);
-        assert_eq!(reader.validate_null(), false);
+        assert!(!reader.validate_null());
     }
 
     #[test]
     fn test_validate_string() {
+        let mut reader: JSONReader = JSONReader::new(br#""a\"b\u00e9" "#);
+        reader.seek(1);
+        assert!(reader.validate_string());
+        assert_eq!(reader.position(), 11);
+        for invalid in [br#""\x""#.as_ref(), br#""\u00g1""#, b"\"tab\t\"", br#""open"#].iter() {
+            let mut reader: JSONReader = JSONReader::new(invalid);
+            reader.seek(1);
+            assert!(!reader.validate_string(), "{}", String::from_utf8_lossy(invalid));
+        }
     }
-}
\ No newline at end of file
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_validate_containers() {
+        let mut reader: JSONReader = JSONReader::new(br#"{"a": [1, {"b": null}]} trailing"#);
+        reader.next();
+        assert!(reader.validate_object());
+        assert_eq!(reader.position(), 22);
+        let mut reader: JSONReader = JSONReader::new(br#"[1, "x"]"#);
+        reader.next();
+        assert!(reader.validate_array());
+        assert!(!reader.validate_object());
+        let mut reader: JSONReader = JSONReader::new(br#"{"a": 1,}"#);
+        reader.next();
+        assert!(!reader.validate_object());
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(JSONReader::new(br#" {"a": [1, 2.5e-3, "x"], "b": {"c": null}} "#).validate());
+        assert!(JSONReader::new(b"[]").validate());
+        assert!(!JSONReader::new(br#"{"a": 1,}"#).validate());
+        assert!(!JSONReader::new(br#"{"a": 1} {"#).validate());
+        assert!(JSONReader::new(br#" "scalar" "#).validate());
+        assert!(!JSONReader::new(b"1 2").validate());
+        assert_eq!(JSONReader::new(br#"{"a": 1}{"b": 2}[3] 4 "five""#).check_concatenated(), Ok(5));
+        assert_eq!(JSONReader::new(b"").check_concatenated(), Ok(0));
+        assert!(JSONReader::new(b"{}{").check_concatenated().is_err());
+        assert_eq!(JSONReader::new(br#"{"a": tru}"#).check(), Err(ParseError{ kind: ErrorKind::InvalidLiteral, offset: 6 }));
+    }
+
+    #[test]
+    fn test_is_number() {
+        for valid in ["0", "-0", "32", "-1.5", "0.25e10", "1E-7", "6.02e+23"].iter() {
+            assert!(is_number(valid.as_bytes()), "{}", valid);
+        }
+        for invalid in ["", "-", "01", "1.", ".5", "1e", "+1", "1.5.2", "0x10", "1e+"].iter() {
+            assert!(!is_number(invalid.as_bytes()), "{}", invalid);
+        }
+    }
+}